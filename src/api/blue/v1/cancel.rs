@@ -0,0 +1,25 @@
+use std::error::Error;
+
+use actix_web::{post, web::Json, HttpResponse};
+use goodmorning_services::structs::{Account, GMServices};
+
+use crate::{
+    functions::from_blue_res,
+    structs::{V1BlueJob, V1BlueResponse},
+    values::RENDER_JOBS,
+};
+
+#[post("/cancel")]
+pub async fn cancel(post: Json<V1BlueJob>) -> HttpResponse {
+    from_blue_res(cancel_task(post).await)
+}
+
+async fn cancel_task(post: Json<V1BlueJob>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    RENDER_JOBS.get().unwrap().cancel(post.id, account.id)?;
+
+    Ok(V1BlueResponse::RenderCancelled { id: post.id })
+}
@@ -1,19 +1,23 @@
-use std::{error::Error, path::PathBuf};
+use std::{collections::HashMap, error::Error, path::PathBuf};
 
 use actix_web::{get, web::Path, HttpResponse};
-use bluemap_singleserve::Map;
 use goodmorning_services::{
-    bindings::services::v1::{V1Error, V1Response},
-    functions::{dir_items, from_res, get_user_dir},
+    bindings::services::v1::V1Error,
+    functions::{dir_items, get_user_dir},
     structs::{Account, GMServices},
 };
 
+use crate::{
+    functions::{from_blue_res, map_exists},
+    structs::{ImportManifest, RenderManifest, V1BlueResponse},
+};
+
 #[get("/diritems/{token}/{path:.*}")]
 pub async fn diritems(path: Path<(String, String)>) -> HttpResponse {
-    from_res(diritems_task(path).await)
+    from_blue_res(diritems_task(path).await)
 }
 
-async fn diritems_task(path: Path<(String, String)>) -> Result<V1Response, Box<dyn Error>> {
+async fn diritems_task(path: Path<(String, String)>) -> Result<V1BlueResponse, Box<dyn Error>> {
     let (token, path) = path.into_inner();
 
     let mut account = Account::v1_get_by_token(&token)
@@ -39,18 +43,25 @@ async fn diritems_task(path: Path<(String, String)>) -> Result<V1Response, Box<d
     }
 
     let mut items = Vec::new();
+    let mut manifests = HashMap::new();
+    let mut imports = HashMap::new();
 
     let base = std::path::Path::new("blue").join(&path);
     let base_abs = get_user_dir(account.id, Some(GMServices::Blue)).join(&preview_path);
 
     for parent in base.ancestors() {
-        if Map::exists(&base_abs.join(parent)).await {
+        if map_exists(&base_abs.join(parent)).await {
             return Err(V1Error::TypeMismatch.into());
         }
     }
 
     for mut item in dir_items(id, &base, true, false).await? {
-        if Map::exists(&base_abs.join(&item.name)).await {
+        if map_exists(&base_abs.join(&item.name)).await {
+            if let Some(manifest) = RenderManifest::load(&base_abs.join(&item.name)).await {
+                manifests.insert(item.name.clone(), manifest);
+            } else if let Some(import) = ImportManifest::load(&base_abs.join(&item.name)).await {
+                imports.insert(item.name.clone(), import);
+            }
             item.is_file = true;
             items.push(item);
         } else if !item.is_file {
@@ -58,5 +69,9 @@ async fn diritems_task(path: Path<(String, String)>) -> Result<V1Response, Box<d
         }
     }
 
-    Ok(V1Response::DirContent { content: items })
+    Ok(V1BlueResponse::DirContent {
+        content: items,
+        manifests,
+        imports,
+    })
 }
@@ -0,0 +1,87 @@
+use std::{error::Error, ffi::OsStr, path::PathBuf};
+
+use actix_web::{
+    post,
+    web::{self, Json},
+    HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::{get_user_dir, has_dotdot},
+    structs::{Account, GMServices, Jobs},
+};
+use tokio::fs;
+
+use crate::{
+    functions::{blue_error, map_exists},
+    structs::{ExportTask, V1BlueExport, V1BlueResponse},
+    values::RENDER_JOBS,
+};
+
+#[post("/export")]
+pub async fn export(post: Json<V1BlueExport>, jobs: web::Data<Jobs>) -> HttpResponse {
+    match export_task(post, jobs).await {
+        Ok(res) => res,
+        Err(e) => blue_error(e),
+    }
+}
+
+async fn export_task(
+    post: Json<V1BlueExport>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    let from_path = std::path::Path::new("blue").join(post.from.trim_matches('/'));
+    let mut to_path = PathBuf::from(post.to.trim_matches('/'));
+    if to_path.extension() != Some(OsStr::new("zip")) {
+        to_path.as_mut_os_string().push(".zip");
+    }
+
+    if has_dotdot(&from_path)
+        || has_dotdot(&to_path)
+        || from_path
+            .iter()
+            .nth(1)
+            .is_some_and(|p| p == OsStr::new(".system"))
+        || to_path
+            .iter()
+            .next()
+            .is_some_and(|p| p == OsStr::new(".system"))
+    {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    if !map_exists(&get_user_dir(account.id, None).join(&from_path)).await {
+        return Err(V1Error::FileNotFound.into());
+    }
+    if fs::try_exists(get_user_dir(account.id, None).join(&to_path)).await? {
+        return Err(V1Error::PathOccupied.into());
+    }
+
+    let task = RENDER_JOBS.get().unwrap().register(ExportTask {
+        from: from_path,
+        to: to_path,
+        user: account.id,
+        job: 0,
+    });
+
+    if post.background {
+        let id = task.job;
+        actix_web::rt::spawn(async move {
+            let _ = RENDER_JOBS.get().unwrap().run(&jobs, &account, task).await;
+        });
+        return Ok(HttpResponse::Ok().json(V1BlueResponse::ExportQueued { id }));
+    }
+
+    let path = task.to.to_string_lossy().to_string();
+    RENDER_JOBS
+        .get()
+        .unwrap()
+        .run(&jobs, &account, task)
+        .await?;
+    Ok(HttpResponse::Ok().json(V1BlueResponse::Exported { path }))
+}
@@ -0,0 +1,108 @@
+use std::{error::Error, ffi::OsStr, path::PathBuf};
+
+use actix_web::{
+    post,
+    web::{self, Json},
+    HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::{get_user_dir, has_dotdot},
+    structs::{Account, GMServices, Jobs},
+};
+use tokio::fs;
+
+use crate::{
+    functions::{blue_error, map_exists, validate_webapp, webroot, ArchiveKind},
+    structs::{ImportTask, V1BlueError, V1BlueImport, V1BlueResponse},
+    values::{RENDER_JOBS, VALUES},
+};
+
+#[post("/import")]
+pub async fn import(post: Json<V1BlueImport>, jobs: web::Data<Jobs>) -> HttpResponse {
+    match import_task(post, jobs).await {
+        Ok(res) => res,
+        Err(e) => blue_error(e),
+    }
+}
+
+async fn import_task(
+    post: Json<V1BlueImport>,
+    jobs: web::Data<Jobs>,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    if VALUES.get().config.webapp_path.is_none() {
+        return Err(V1BlueError::InvalidImport {
+            reason: "imports are not enabled on this server".to_string(),
+        }
+        .into());
+    }
+
+    let from_path = PathBuf::from(post.from.trim_start_matches('/'));
+    let to_path = std::path::Path::new("blue").join(post.to.trim_matches('/'));
+
+    if has_dotdot(&from_path)
+        || has_dotdot(&to_path)
+        || from_path
+            .iter()
+            .next()
+            .is_some_and(|p| p == OsStr::new(".system"))
+        || to_path
+            .iter()
+            .nth(1)
+            .is_some_and(|p| p == OsStr::new(".system"))
+    {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    let from_abs = get_user_dir(account.id, None).join(&from_path);
+    let to_abs = get_user_dir(account.id, None).join(&to_path);
+
+    if !fs::try_exists(&from_abs).await? {
+        return Err(V1Error::FileNotFound.into());
+    }
+    if fs::try_exists(&to_abs).await? {
+        return Err(V1Error::PathOccupied.into());
+    }
+    for parent in to_path.ancestors().skip(1) {
+        if map_exists(&get_user_dir(account.id, None).join(parent)).await {
+            return Err(V1Error::PathOccupied.into());
+        }
+    }
+
+    // archives are checked once extracted, in the job
+    if ArchiveKind::from_path(&from_abs).is_none() {
+        if !fs::metadata(&from_abs).await?.is_dir() {
+            return Err(V1Error::TypeMismatch.into());
+        }
+        validate_webapp(&webroot(&from_abs).await).await?;
+    }
+
+    let task = RENDER_JOBS.get().unwrap().register(ImportTask {
+        from: from_path,
+        to: to_path,
+        user: account.id,
+        job: 0,
+    });
+
+    if post.background {
+        let id = task.job;
+        actix_web::rt::spawn(async move {
+            let _ = RENDER_JOBS.get().unwrap().run(&jobs, &account, task).await;
+        });
+        return Ok(HttpResponse::Ok().json(V1BlueResponse::ImportQueued { id }));
+    }
+
+    RENDER_JOBS
+        .get()
+        .unwrap()
+        .run(&jobs, &account, task)
+        .await?;
+    Ok(HttpResponse::Ok().json(V1BlueResponse::Imported {
+        path: post.to.trim_matches('/').to_string(),
+    }))
+}
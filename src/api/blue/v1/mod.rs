@@ -1,12 +1,44 @@
 use actix_web::Scope;
 
+mod cancel;
 mod diritems;
+mod export;
+mod import;
 mod presets;
+mod progress;
+mod queue;
+mod reload;
 mod render;
+mod schedules;
+mod snapshots;
+mod status;
+mod userpresets;
+mod watch;
+mod worldinfo;
 
 pub fn scope() -> Scope {
     Scope::new("v1")
         .service(diritems::diritems)
         .service(render::render)
+        .service(import::import)
+        .service(export::export)
         .service(presets::presets)
+        .service(presets::presets_user)
+        .service(presets::preview)
+        .service(userpresets::preset)
+        .service(userpresets::save)
+        .service(userpresets::delete)
+        .service(status::status)
+        .service(progress::progress)
+        .service(queue::queue)
+        .service(cancel::cancel)
+        .service(reload::reload)
+        .service(schedules::schedules)
+        .service(schedules::save)
+        .service(schedules::delete)
+        .service(snapshots::snapshots)
+        .service(snapshots::delete)
+        .service(watch::state)
+        .service(watch::set)
+        .service(worldinfo::worldinfo)
 }
@@ -1,12 +1,67 @@
-use actix_web::{get, HttpResponse};
-use goodmorning_services::bindings::services::v1::V1Response;
+use std::error::Error;
 
-use crate::values::{BLUE_CONFIG, PRESETS};
+use actix_files::NamedFile;
+use actix_web::{get, web::Path, HttpRequest, HttpResponse};
+use goodmorning_services::structs::{Account, GMServices};
+
+use crate::{
+    functions::{blue_error, from_blue_res, preset_preview, user_preset_info, user_presets},
+    structs::{V1BlueError, V1BlueResponse},
+    values::VALUES,
+};
 
 #[get("/presets")]
 pub async fn presets() -> HttpResponse {
-    HttpResponse::Ok().json(V1Response::BluePresets {
-        default: BLUE_CONFIG.get().unwrap().default_preset.clone(),
-        all: PRESETS.get().unwrap().clone(),
+    HttpResponse::Ok().json(V1BlueResponse::Presets {
+        default: VALUES.get().config.default_preset.clone(),
+        all: VALUES.get().presets.to_vec(),
+        user: Vec::new(),
+        info: VALUES.get().preset_info.to_vec(),
     })
 }
+
+#[get("/presets/{token}")]
+pub async fn presets_user(token: Path<String>) -> HttpResponse {
+    from_blue_res(presets_user_task(token).await)
+}
+
+async fn presets_user_task(token: Path<String>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    let user = user_presets(account.id).await;
+    let mut info = VALUES.get().preset_info.to_vec();
+    for name in user.iter() {
+        info.push(user_preset_info(account.id, name).await);
+    }
+
+    Ok(V1BlueResponse::Presets {
+        default: VALUES.get().config.default_preset.clone(),
+        all: VALUES.get().presets.to_vec(),
+        user,
+        info,
+    })
+}
+
+#[get("/presetpreview/{name}")]
+pub async fn preview(name: Path<String>, req: HttpRequest) -> HttpResponse {
+    match preview_task(name, &req).await {
+        Ok(res) => res,
+        Err(e) => blue_error(e),
+    }
+}
+
+async fn preview_task(
+    name: Path<String>,
+    req: &HttpRequest,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    if !VALUES.get().presets.contains(&name) {
+        return Err(V1BlueError::PresetNotFound.into());
+    }
+
+    match preset_preview(&name) {
+        Some(path) => Ok(NamedFile::open_async(path).await?.into_response(req)),
+        None => Err(V1BlueError::PresetNotFound.into()),
+    }
+}
@@ -0,0 +1,61 @@
+use std::{error::Error, time::Duration};
+
+use actix_web::{get, web::Bytes, web::Path, HttpResponse};
+use goodmorning_services::structs::{Account, GMServices};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    functions::blue_error,
+    structs::{V1BlueError, V1BlueResponse},
+    values::RENDER_JOBS,
+};
+
+/// Streams the status of a render as server-sent events, one event a second until it finishes.
+#[get("/progress/{token}/{id}")]
+pub async fn progress(path: Path<(String, u64)>) -> HttpResponse {
+    match progress_task(path).await {
+        Ok(res) => res,
+        Err(e) => blue_error(e),
+    }
+}
+
+async fn progress_task(path: Path<(String, u64)>) -> Result<HttpResponse, Box<dyn Error>> {
+    let (token, id) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    if RENDER_JOBS.get().unwrap().status(id, account.id).is_none() {
+        return Err(V1BlueError::JobNotFound.into());
+    }
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, actix_web::Error>>(4);
+
+    actix_web::rt::spawn(async move {
+        while let Some((status, current)) = RENDER_JOBS.get().unwrap().status(id, account.id) {
+            let finished = status.is_finished();
+            let event = format!(
+                "data: {}\n\n",
+                serde_json::to_string(&V1BlueResponse::RenderStatus {
+                    id,
+                    status,
+                    progress: current,
+                })
+                .unwrap()
+            );
+
+            if tx.send(Ok(Bytes::from(event))).await.is_err() || finished {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(ReceiverStream::new(rx)))
+}
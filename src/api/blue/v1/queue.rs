@@ -0,0 +1,28 @@
+use std::error::Error;
+
+use actix_web::{get, web::Path, HttpResponse};
+use goodmorning_services::structs::{Account, GMServices};
+
+use crate::{
+    functions::{from_blue_res, queue_estimate},
+    structs::V1BlueResponse,
+};
+
+/// Where a job stands in the queue, and when it should start and finish.
+#[get("/queue/{token}/{id}")]
+pub async fn queue(path: Path<(String, u64)>) -> HttpResponse {
+    from_blue_res(queue_task(path).await)
+}
+
+async fn queue_task(path: Path<(String, u64)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, id) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    Ok(V1BlueResponse::QueueEstimate {
+        id,
+        estimate: queue_estimate(id, &account).await?,
+    })
+}
@@ -0,0 +1,36 @@
+use std::error::Error;
+
+use actix_web::{post, web::Json, HttpResponse};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    structs::{Account, GMServices},
+};
+
+use crate::{
+    functions::from_blue_res,
+    structs::{V1BlueError, V1BlueResponse, V1BlueToken},
+    values::{self, VALUES},
+};
+
+#[post("/reload")]
+pub async fn reload(post: Json<V1BlueToken>) -> HttpResponse {
+    from_blue_res(reload_task(post).await)
+}
+
+async fn reload_task(post: Json<V1BlueToken>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    if !VALUES.get().config.admins.contains(&account.id) {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    values::reload()
+        .await
+        .map_err(|reason| V1BlueError::ReloadFailed { reason })?;
+
+    Ok(V1BlueResponse::Reloaded {
+        presets: VALUES.get().presets.len(),
+    })
+}
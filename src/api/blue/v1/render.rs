@@ -1,30 +1,41 @@
-use std::{error::Error, ffi::OsStr, path::PathBuf, time::Duration};
+use std::{error::Error, ffi::OsStr, path::PathBuf};
 
 use actix_web::{
     post,
     web::{self, Json},
     HttpResponse,
 };
+use bluemap_singleserve::Map;
 use goodmorning_services::{
-    bindings::services::v1::{AccessType, V1Error, V1Render, V1Response},
-    functions::{from_res, get_user_dir, get_usersys_dir, has_dotdot},
+    bindings::services::v1::{AccessType, V1Error, V1Response},
+    functions::{get_user_dir, get_usersys_dir, has_dotdot},
     structs::{Account, GMServices, Jobs},
     traits::CollectionItem,
-    ACCOUNTS, MAX_CONCURRENT, QUEUE_LIMIT, QUEUE_PRESETS,
+    ACCOUNTS,
 };
 use tokio::fs;
 
-use crate::{structs::RenderTask, values::BLUE_CONFIG};
+use crate::{
+    functions::{
+        blue_error, check_world, estimate_render, override_lines, parse_dimension,
+        preset_dimension, preset_path, validate_preset_name, ArchiveKind, USER_PRESET_PREFIX,
+    },
+    structs::{RenderManifest, RenderTask, V1BlueError, V1BlueRender, V1BlueResponse},
+    values::{RENDER_JOBS, VALUES},
+};
 
 #[post("/render")]
-pub async fn render(post: Json<V1Render>, jobs: web::Data<Jobs>) -> HttpResponse {
-    from_res(render_task(post, jobs).await)
+pub async fn render(post: Json<V1BlueRender>, jobs: web::Data<Jobs>) -> HttpResponse {
+    match render_task(post, jobs).await {
+        Ok(res) => res,
+        Err(e) => blue_error(e),
+    }
 }
 
 async fn render_task(
-    post: Json<V1Render>,
+    post: Json<V1BlueRender>,
     jobs: web::Data<Jobs>,
-) -> Result<V1Response, Box<dyn Error>> {
+) -> Result<HttpResponse, Box<dyn Error>> {
     let post = post.into_inner();
 
     let mut account = Account::v1_get_by_token(&post.token)
@@ -109,36 +120,122 @@ async fn render_task(
         return Err(V1Error::PermissionDenied.into());
     }
 
-    if fs::try_exists(&get_user_dir(account.id, Some(GMServices::Blue)).join(&to_path)).await? {
+    let preset = post.preset.trim_start_matches('/').to_string();
+    if let Some(name) = preset.strip_prefix(USER_PRESET_PREFIX) {
+        validate_preset_name(name)?;
+    }
+    if !fs::try_exists(preset_path(account.id, &preset)).await? {
+        return Err(V1BlueError::PresetNotFound.into());
+    }
+
+    let to_abs = get_user_dir(account.id, None).join(&to_path);
+    let mut area = post.area.resolve()?;
+    let mut overrides = post.overrides.clone().unwrap_or_default();
+    override_lines(&overrides)?;
+    let mut dimensions = Vec::new();
+    for dimension in post.dimensions.iter() {
+        let dimension = parse_dimension(dimension)?;
+        if !dimensions.contains(&dimension) {
+            dimensions.push(dimension);
+        }
+    }
+
+    let snapshot_limit = VALUES.get().config.snapshot_limit(&account.limit);
+    if post.keep_versions == Some(true) && snapshot_limit == 0 {
+        return Err(V1BlueError::SnapshotsDisabled.into());
+    }
+
+    let mut previous = None;
+    let mut keep_versions = post.keep_versions;
+    if post.update {
+        if !Map::exists(&to_abs).await {
+            return Err(V1Error::FileNotFound.into());
+        }
+
+        // carry over what the map was first rendered with, unless given again
+        if let Some(manifest) = RenderManifest::load(&to_abs).await {
+            if dimensions.is_empty() {
+                dimensions = manifest.dimensions.clone();
+            }
+            if area.is_unbounded() {
+                area = manifest.area;
+            }
+            if post.overrides.is_none() {
+                overrides = manifest.overrides.clone();
+            }
+            keep_versions = keep_versions.or(Some(manifest.keep_versions));
+            previous = Some(manifest);
+        }
+    } else if fs::try_exists(&to_abs).await?
+        && !(post.keep_versions == Some(true) && Map::exists(&to_abs).await)
+    {
         return Err(V1Error::PathOccupied.into());
     }
+    let keep_versions = if keep_versions.unwrap_or(false) {
+        snapshot_limit
+    } else {
+        0
+    };
+
+    let from_abs = get_user_dir(account.id, None).join(&from_path);
+    let render_dimensions = if dimensions.is_empty() {
+        vec![preset_dimension(&preset_path(account.id, &preset)).await]
+    } else {
+        dimensions.clone()
+    };
+    if ArchiveKind::from_path(&from_abs).is_some() {
+        // archives are checked once extracted, when the render runs
+        if !fs::metadata(&from_abs).await?.is_file() {
+            return Err(V1Error::TypeMismatch.into());
+        }
+
+        if post.dry_run {
+            return Err(V1BlueError::NoEstimate {
+                reason: "archives can only be estimated once extracted".to_string(),
+            }
+            .into());
+        }
+    } else {
+        check_world(&from_abs, &render_dimensions).await?;
+    }
+
+    let task = RenderTask {
+        from: from_path,
+        to: to_path,
+        user: account.id,
+        preset,
+        job: 0,
+        update: post.update,
+        dimensions,
+        area,
+        overrides,
+        keep_versions,
+    };
+
+    if post.dry_run {
+        let since = previous
+            .filter(|manifest| manifest.renders_like(&task))
+            .map(|manifest| manifest.started);
+        return Ok(HttpResponse::Ok().json(V1BlueResponse::RenderEstimate {
+            estimate: estimate_render(&account, &from_abs, &render_dimensions, &area, since).await,
+        }));
+    }
+
+    let task = RENDER_JOBS.get().unwrap().register(task);
+
+    if post.background {
+        let id = task.job;
+        actix_web::rt::spawn(async move {
+            let _ = RENDER_JOBS.get().unwrap().run(&jobs, &account, task).await;
+        });
+        return Ok(HttpResponse::Ok().json(V1BlueResponse::RenderQueued { id }));
+    }
 
-    let mut res = jobs
-        .run_with_limit(
-            account.id,
-            Box::new(RenderTask {
-                from: from_path,
-                to: to_path,
-                user: account.id,
-                preset: post.preset.trim_start_matches('/').to_string(),
-            }),
-            QUEUE_PRESETS
-                .get()
-                .unwrap()
-                .get(&account.limit)
-                .map(|c| c.max_concurrent)
-                .unwrap_or(*MAX_CONCURRENT.get().unwrap()),
-            QUEUE_PRESETS
-                .get()
-                .unwrap()
-                .get(&account.limit)
-                .map(|c| c.queue_limit)
-                .unwrap_or(*QUEUE_LIMIT.get().unwrap()),
-            goodmorning_services::bindings::structs::ApiVer::V1,
-            Duration::from_secs(BLUE_CONFIG.get().unwrap().render_timeout),
-        )
-        .await
-        .as_v1()?;
+    let mut res = RENDER_JOBS
+        .get()
+        .unwrap()
+        .run(&jobs, &account, task)
+        .await?;
 
     if symlinked_account {
         if let V1Response::BlueRendered { newpath, .. } = &mut res {
@@ -146,5 +243,5 @@ async fn render_task(
         }
     }
 
-    Ok(res)
+    Ok(HttpResponse::Ok().json(res))
 }
@@ -0,0 +1,138 @@
+use std::{error::Error, ffi::OsStr, path::PathBuf};
+
+use actix_web::{
+    get, post,
+    web::{Json, Path},
+    HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::{get_user_dir, has_dotdot},
+    structs::{Account, GMServices},
+};
+use tokio::fs;
+
+use crate::{
+    functions::{from_blue_res, now, preset_path, validate_preset_name, USER_PRESET_PREFIX},
+    structs::{
+        Cron, RenderSchedule, V1BlueError, V1BlueResponse, V1BlueScheduleDelete,
+        V1BlueScheduleSave, SCHEDULE_LIMIT,
+    },
+};
+
+#[get("/schedules/{token}")]
+pub async fn schedules(path: Path<String>) -> HttpResponse {
+    from_blue_res(schedules_task(path).await)
+}
+
+async fn schedules_task(path: Path<String>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&path.into_inner())
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    Ok(V1BlueResponse::Schedules {
+        schedules: RenderSchedule::of_user(account.id).await?,
+    })
+}
+
+/// Creates a schedule, or replaces the settings of an existing one keeping its last run.
+#[post("/schedule/save")]
+pub async fn save(post: Json<V1BlueScheduleSave>) -> HttpResponse {
+    from_blue_res(save_task(post).await)
+}
+
+async fn save_task(post: Json<V1BlueScheduleSave>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let post = post.into_inner();
+
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    let from_path = PathBuf::from(post.from.trim_start_matches('/'));
+    let to_path = std::path::Path::new("blue").join(post.to.trim_matches('/'));
+
+    if has_dotdot(&from_path)
+        || has_dotdot(&to_path)
+        || from_path
+            .iter()
+            .nth(1)
+            .is_some_and(|p| p == OsStr::new(".system"))
+        || to_path
+            .iter()
+            .nth(1)
+            .is_some_and(|p| p == OsStr::new(".system"))
+        || has_dotdot(&PathBuf::from(&post.preset))
+    {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    let preset = post.preset.trim_start_matches('/').to_string();
+    if let Some(name) = preset.strip_prefix(USER_PRESET_PREFIX) {
+        validate_preset_name(name)?;
+    }
+    if !fs::try_exists(preset_path(account.id, &preset)).await? {
+        return Err(V1BlueError::PresetNotFound.into());
+    }
+    if !fs::try_exists(get_user_dir(account.id, None).join(&from_path)).await? {
+        return Err(V1Error::FileNotFound.into());
+    }
+
+    let cron =
+        Cron::parse(&post.schedule).map_err(|reason| V1BlueError::InvalidSchedule { reason })?;
+    let next_run = cron.next_after(now());
+    if next_run.is_none() {
+        return Err(V1BlueError::InvalidSchedule {
+            reason: "the schedule never runs".to_string(),
+        }
+        .into());
+    }
+
+    let existing = RenderSchedule::of_user(account.id).await?;
+    let (id, last_run) = match post.id {
+        Some(id) => match existing.into_iter().find(|schedule| schedule.id == id) {
+            Some(schedule) => (id, schedule.last_run),
+            None => return Err(V1BlueError::ScheduleNotFound.into()),
+        },
+        None if existing.len() >= SCHEDULE_LIMIT => {
+            return Err(V1BlueError::TooManySchedules {
+                limit: SCHEDULE_LIMIT,
+            }
+            .into())
+        }
+        None => (fastrand::u64(..1 << 53), None),
+    };
+
+    let schedule = RenderSchedule {
+        id,
+        user: account.id,
+        from: from_path,
+        to: to_path,
+        preset,
+        schedule: post.schedule.trim().to_string(),
+        paused: post.paused,
+        next_run,
+        last_run,
+    };
+    schedule.save().await?;
+
+    Ok(V1BlueResponse::ScheduleSaved { schedule })
+}
+
+#[post("/schedule/delete")]
+pub async fn delete(post: Json<V1BlueScheduleDelete>) -> HttpResponse {
+    from_blue_res(delete_task(post).await)
+}
+
+async fn delete_task(post: Json<V1BlueScheduleDelete>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    if !RenderSchedule::delete(account.id, post.id).await? {
+        return Err(V1BlueError::ScheduleNotFound.into());
+    }
+
+    Ok(V1BlueResponse::ScheduleDeleted { id: post.id })
+}
@@ -0,0 +1,61 @@
+use std::{error::Error, path::PathBuf};
+
+use actix_web::{
+    get, post,
+    web::{Json, Path},
+    HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::has_dotdot,
+    structs::{Account, GMServices},
+};
+
+use crate::{
+    functions::{delete_snapshot, from_blue_res, list_snapshots},
+    structs::{V1BlueResponse, V1BlueSnapshot},
+    values::VALUES,
+};
+
+/// `path` relative to the owner's directory, from a map path under `blue/`.
+fn map_path(path: &str) -> Result<PathBuf, V1Error> {
+    let path = std::path::Path::new("blue").join(path.trim_matches('/'));
+    if has_dotdot(&path) {
+        return Err(V1Error::PermissionDenied);
+    }
+    Ok(path)
+}
+
+#[get("/snapshots/{token}/{path:.*}")]
+pub async fn snapshots(path: Path<(String, String)>) -> HttpResponse {
+    from_blue_res(snapshots_task(path).await)
+}
+
+async fn snapshots_task(path: Path<(String, String)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, path) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    Ok(V1BlueResponse::Snapshots {
+        snapshots: list_snapshots(account.id, &map_path(&path)?).await,
+        limit: VALUES.get().config.snapshot_limit(&account.limit),
+    })
+}
+
+#[post("/snapshot/delete")]
+pub async fn delete(post: Json<V1BlueSnapshot>) -> HttpResponse {
+    from_blue_res(delete_task(post).await)
+}
+
+async fn delete_task(post: Json<V1BlueSnapshot>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    delete_snapshot(account.id, &map_path(&post.path)?, post.id).await?;
+    Ok(V1BlueResponse::SnapshotDeleted { id: post.id })
+}
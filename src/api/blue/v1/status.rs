@@ -0,0 +1,32 @@
+use std::error::Error;
+
+use actix_web::{get, web::Path, HttpResponse};
+use goodmorning_services::structs::{Account, GMServices};
+
+use crate::{
+    functions::from_blue_res,
+    structs::{V1BlueError, V1BlueResponse},
+    values::RENDER_JOBS,
+};
+
+#[get("/status/{token}/{id}")]
+pub async fn status(path: Path<(String, u64)>) -> HttpResponse {
+    from_blue_res(status_task(path).await)
+}
+
+async fn status_task(path: Path<(String, u64)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, id) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    match RENDER_JOBS.get().unwrap().status(id, account.id) {
+        Some((current, progress)) => Ok(V1BlueResponse::RenderStatus {
+            id,
+            status: current,
+            progress,
+        }),
+        None => Err(V1BlueError::JobNotFound.into()),
+    }
+}
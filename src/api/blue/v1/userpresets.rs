@@ -0,0 +1,93 @@
+use std::error::Error;
+
+use actix_web::{
+    get, post,
+    web::{Json, Path},
+    HttpResponse,
+};
+use goodmorning_services::structs::{Account, GMServices};
+use tokio::fs;
+
+use crate::{
+    functions::{
+        from_blue_res, user_presets, user_presets_dir, validate_preset, validate_preset_name,
+        USER_PRESET_LIMIT,
+    },
+    structs::{V1BlueError, V1BluePresetDelete, V1BluePresetSave, V1BlueResponse},
+};
+
+#[get("/preset/{token}/{name}")]
+pub async fn preset(path: Path<(String, String)>) -> HttpResponse {
+    from_blue_res(preset_task(path).await)
+}
+
+async fn preset_task(path: Path<(String, String)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, name) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    validate_preset_name(&name)?;
+
+    match fs::read_to_string(user_presets_dir(account.id).join(&name)).await {
+        Ok(content) => Ok(V1BlueResponse::PresetContent { name, content }),
+        Err(_) => Err(V1BlueError::PresetNotFound.into()),
+    }
+}
+
+/// Uploads a new preset or replaces an existing one.
+#[post("/preset/save")]
+pub async fn save(post: Json<V1BluePresetSave>) -> HttpResponse {
+    from_blue_res(save_task(post).await)
+}
+
+async fn save_task(post: Json<V1BluePresetSave>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let post = post.into_inner();
+
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    validate_preset_name(&post.name)?;
+    validate_preset(&post.content)?;
+
+    let existing = user_presets(account.id).await;
+    if existing.len() >= USER_PRESET_LIMIT && !existing.contains(&post.name) {
+        return Err(V1BlueError::TooManyPresets {
+            limit: USER_PRESET_LIMIT,
+        }
+        .into());
+    }
+
+    let dir = user_presets_dir(account.id);
+    fs::create_dir_all(&dir).await?;
+    fs::write(dir.join(&post.name), post.content).await?;
+
+    Ok(V1BlueResponse::PresetSaved { name: post.name })
+}
+
+#[post("/preset/delete")]
+pub async fn delete(post: Json<V1BluePresetDelete>) -> HttpResponse {
+    from_blue_res(delete_task(post).await)
+}
+
+async fn delete_task(post: Json<V1BluePresetDelete>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let post = post.into_inner();
+
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    validate_preset_name(&post.name)?;
+
+    if fs::remove_file(user_presets_dir(account.id).join(&post.name))
+        .await
+        .is_err()
+    {
+        return Err(V1BlueError::PresetNotFound.into());
+    }
+
+    Ok(V1BlueResponse::PresetDeleted { name: post.name })
+}
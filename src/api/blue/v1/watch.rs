@@ -0,0 +1,102 @@
+use std::{error::Error, path::PathBuf};
+
+use actix_web::{
+    get, post,
+    web::{Json, Path},
+    HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::{get_user_dir, has_dotdot},
+    structs::{Account, GMServices},
+};
+
+use crate::{
+    functions::{from_blue_res, map_exists},
+    structs::{MapWatch, RenderManifest, V1BlueError, V1BlueResponse, V1BlueWatch, WatchState},
+};
+
+/// `path` relative to the owner's directory, from a map path under `blue/`.
+fn map_path(path: &str) -> Result<PathBuf, V1Error> {
+    let path = std::path::Path::new("blue").join(path.trim_matches('/'));
+    if has_dotdot(&path) {
+        return Err(V1Error::PermissionDenied);
+    }
+    Ok(path)
+}
+
+fn watch_res(watch: Option<MapWatch>) -> V1BlueResponse {
+    match watch {
+        Some(watch) => V1BlueResponse::Watch {
+            state: watch.state(),
+            last_queued: watch.last_queued,
+            last_job: watch.last_job,
+        },
+        None => V1BlueResponse::Watch {
+            state: WatchState::Off,
+            last_queued: None,
+            last_job: None,
+        },
+    }
+}
+
+#[get("/watch/{token}/{path:.*}")]
+pub async fn state(path: Path<(String, String)>) -> HttpResponse {
+    from_blue_res(state_task(path).await)
+}
+
+async fn state_task(path: Path<(String, String)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, path) = path.into_inner();
+
+    let account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?;
+
+    let map = map_path(&path)?;
+    Ok(watch_res(
+        MapWatch::find(account.id, &map.to_string_lossy()).await?,
+    ))
+}
+
+/// Turns automatic updates of a map on, off, or pauses them keeping the setting.
+#[post("/watch")]
+pub async fn set(post: Json<V1BlueWatch>) -> HttpResponse {
+    from_blue_res(set_task(post).await)
+}
+
+async fn set_task(post: Json<V1BlueWatch>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let account = Account::v1_get_by_token(&post.token)
+        .await?
+        .v1_restrict_verified()?
+        .v1_contains(&GMServices::Blue)?;
+
+    let map = map_path(&post.path)?;
+    let map_str = map.to_string_lossy().to_string();
+
+    if post.state == WatchState::Off {
+        MapWatch::delete(account.id, &map_str).await?;
+        return Ok(watch_res(None));
+    }
+
+    let map_abs = get_user_dir(account.id, None).join(&map);
+    if !map_exists(&map_abs).await {
+        return Err(V1Error::FileNotFound.into());
+    }
+    if RenderManifest::load(&map_abs).await.is_none() {
+        return Err(V1BlueError::NotRendered.into());
+    }
+
+    let mut watch = MapWatch::find(account.id, &map_str)
+        .await?
+        .unwrap_or(MapWatch {
+            user: account.id,
+            map,
+            paused: false,
+            last_queued: None,
+            last_job: None,
+        });
+    watch.paused = post.state == WatchState::Paused;
+    watch.save().await?;
+
+    Ok(watch_res(Some(watch)))
+}
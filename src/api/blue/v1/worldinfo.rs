@@ -0,0 +1,61 @@
+use std::{error::Error, path::PathBuf};
+
+use actix_web::{get, web::Path, HttpResponse};
+use goodmorning_services::{
+    bindings::services::v1::{AccessType, V1Error},
+    functions::{get_user_dir, has_dotdot},
+    structs::{Account, GMServices},
+};
+
+use crate::{
+    functions::{from_blue_res, world_info},
+    structs::V1BlueResponse,
+};
+
+#[get("/worldinfo/{token}/{path:.*}")]
+pub async fn worldinfo(path: Path<(String, String)>) -> HttpResponse {
+    from_blue_res(worldinfo_task(path).await)
+}
+
+async fn worldinfo_task(path: Path<(String, String)>) -> Result<V1BlueResponse, Box<dyn Error>> {
+    let (token, path) = path.into_inner();
+
+    let mut account = Account::v1_get_by_token(&token)
+        .await?
+        .v1_contains(&GMServices::Blue)?
+        .v1_restrict_verified()?;
+
+    let mut world_path = PathBuf::from(&path);
+    let id = account.id;
+
+    if let ["Shared", user, ..] = world_path
+        .iter()
+        .map(|s| s.to_str().unwrap())
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        account = if let Some(account) = Account::find_by_username(user.to_string()).await? {
+            account.v1_restrict_verified()?
+        } else {
+            return Err(V1Error::FileNotFound.into());
+        };
+
+        if !account
+            .access
+            .get(AccessType::File.as_str())
+            .is_some_and(|set| set.contains(&id))
+        {
+            return Err(V1Error::FileNotFound.into());
+        }
+        world_path = world_path.iter().skip(2).collect();
+    }
+
+    if has_dotdot(&world_path) || world_path.iter().next().is_some_and(|p| p == ".system") {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    let world = get_user_dir(account.id, Some(GMServices::Blue)).join(&world_path);
+    Ok(V1BlueResponse::WorldInfo {
+        info: world_info(&world, account.id == id).await?,
+    })
+}
@@ -5,7 +5,7 @@ use goodmorning_services::bindings::services::v1::{V1Error, V1Response, V1TokenO
 use goodmorning_services::{functions::*, structs::*, traits::CollectionItem, *};
 use tokio::fs;
 
-use crate::values::BLUE_CONFIG;
+use crate::values::VALUES;
 
 #[post("/create")]
 pub async fn create(post: Json<V1TokenOnly>) -> HttpResponse {
@@ -13,7 +13,7 @@ pub async fn create(post: Json<V1TokenOnly>) -> HttpResponse {
 }
 
 async fn create_task(post: Json<V1TokenOnly>) -> Result<V1Response, Box<dyn Error>> {
-    if !BLUE_CONFIG.get().unwrap().allow_create {
+    if !VALUES.get().config.allow_create {
         return Err(V1Error::FeatureDisabled.into());
     }
 
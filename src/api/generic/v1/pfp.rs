@@ -10,7 +10,7 @@ use goodmorning_services::{
 };
 use tokio::fs;
 
-use crate::values::BLUE_CONFIG;
+use crate::values::VALUES;
 
 #[get("/pfp/id/{id}")]
 pub async fn pfp(id: web::Path<i64>, req: HttpRequest) -> HttpResponse {
@@ -21,7 +21,8 @@ pub async fn pfp(id: web::Path<i64>, req: HttpRequest) -> HttpResponse {
 }
 
 async fn pfp_task(id: web::Path<i64>, req: HttpRequest) -> Result<HttpResponse, Box<dyn Error>> {
-    let conf = BLUE_CONFIG.get().unwrap();
+    let values = VALUES.get();
+    let conf = &values.config;
     let path = get_user_dir(*id, None)
         .join(
             conf.alternate_pfp
@@ -42,11 +43,9 @@ async fn pfp_task(id: web::Path<i64>, req: HttpRequest) -> Result<HttpResponse,
     }
 
     if !fs::try_exists(&path).await? {
-        return Ok(
-            NamedFile::open_async(&BLUE_CONFIG.get().unwrap().pfp_default)
-                .await?
-                .into_response(&req),
-        );
+        return Ok(NamedFile::open_async(&VALUES.get().config.pfp_default)
+            .await?
+            .into_response(&req));
     }
 
     Ok(NamedFile::open_async(path).await?.into_response(&req))
@@ -76,7 +75,8 @@ async fn pfp_name_task(
         return Ok(from_res::<V1Response>(Err(V1Error::NotCreated.into())));
     }
 
-    let conf = BLUE_CONFIG.get().unwrap();
+    let values = VALUES.get();
+    let conf = &values.config;
     let path = get_user_dir(account.id, None)
         .join(
             conf.alternate_pfp
@@ -15,7 +15,7 @@ use actix_web::{HttpRequest, HttpResponse};
 use goodmorning_services::structs::Account;
 use yew::{function_component, html, Html, Properties};
 
-use crate::values::{BLUE_CONFIG, TOPBAR_URLS};
+use crate::values::VALUES;
 
 // pub const TOPBAR_LOGGEDOUT: &str = r#"
 //     <div id="top-bar">
@@ -38,7 +38,7 @@ pub fn TopbarLoggedin(props: &TopbarLoggedinProps) -> Html {
     <div id="top-bar">
       <div id="top-bar-left">
     <a href="/" id="top-bar-icon"><img src="/static/images/logo.webp" alt="" width="30"/></a>
-        {Html::from_html_unchecked(implicit_clone::unsync::IString::Static(TOPBAR_URLS.get().unwrap()))}
+        {Html::from_html_unchecked(implicit_clone::unsync::IString::from(VALUES.get().topbar_urls.to_string()))}
       </div>
       <div id="top-bar-right">
         <img src="/static/icons/logout.svg" id="logout" alt="" width="15" />
@@ -93,7 +93,7 @@ pub async fn topbar_option_from_token(
                 Some(account) => account,
                 None => {
                     return Ok(Err(NamedFile::open_async(
-                        std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path)
+                        std::path::Path::new(&VALUES.get().config.static_path)
                             .join("html/been-loggedout.html"),
                     )
                     .await?
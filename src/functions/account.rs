@@ -0,0 +1,20 @@
+use std::error::Error;
+
+use goodmorning_services::{
+    structs::Account, ACCOUNTS, MAX_CONCURRENT, QUEUE_LIMIT, QUEUE_PRESETS,
+};
+use mongodb::bson::doc;
+
+/// Looks up the account a background render runs under, for its queue limits.
+pub async fn account_by_id(id: i64) -> Result<Option<Account>, Box<dyn Error>> {
+    Ok(ACCOUNTS.get().unwrap().find_one(doc! { "_id": id }).await?)
+}
+
+/// Jobs an account on limit tier `limit` may run at once and have waiting, as `(max_concurrent,
+/// queue_limit)`.
+pub fn queue_limits(limit: &str) -> (usize, usize) {
+    match QUEUE_PRESETS.get().unwrap().get(limit) {
+        Some(queue) => (queue.max_concurrent, queue.queue_limit),
+        None => (*MAX_CONCURRENT.get().unwrap(), *QUEUE_LIMIT.get().unwrap()),
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Files and folders an archive may hold, against archives of millions of empty entries.
+const MAX_ENTRIES: usize = 500_000;
+/// How deep below the extracted root a world or webapp is looked for.
+const ROOT_DEPTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where an archive entry goes under `to`, `None` for absolute paths or paths climbing out of it.
+fn entry_path(to: &Path, name: &Path) -> Option<PathBuf> {
+    let mut path = to.to_path_buf();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    (path != to).then_some(path)
+}
+
+/// Extraction state shared by every entry, enforcing the limits.
+struct Extractor<'a> {
+    to: &'a Path,
+    remaining: u64,
+    entries: usize,
+    stop: &'a dyn Fn() -> bool,
+}
+
+impl Extractor<'_> {
+    fn next_entry(&mut self) -> io::Result<()> {
+        if (self.stop)() {
+            return Err(io::Error::other("extraction stopped"));
+        }
+
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            return Err(io::Error::other(format!(
+                "archive has more than {MAX_ENTRIES} entries"
+            )));
+        }
+        Ok(())
+    }
+
+    fn dir(&mut self, name: &Path) -> io::Result<()> {
+        self.next_entry()?;
+        if let Some(path) = entry_path(self.to, name) {
+            fs::create_dir_all(path)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one file, counting what is actually decompressed rather than the size the archive
+    /// claims.
+    fn file(&mut self, name: &Path, content: &mut impl Read) -> io::Result<()> {
+        self.next_entry()?;
+        let Some(path) = entry_path(self.to, name) else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let written = io::copy(
+            &mut content.take(self.remaining + 1),
+            &mut File::create(path)?,
+        )?;
+        if written > self.remaining {
+            return Err(io::Error::other(
+                "archive is larger than the extraction limit",
+            ));
+        }
+        self.remaining -= written;
+        Ok(())
+    }
+}
+
+/// Extracts an archive into `to`, blocking.
+///
+/// Entries that would land outside of `to` and links are skipped, extraction fails once more than
+/// `max_size` bytes have been written or `stop` returns true.
+pub fn extract_archive(
+    archive: &Path,
+    to: &Path,
+    max_size: u64,
+    stop: &dyn Fn() -> bool,
+) -> io::Result<()> {
+    let kind = ArchiveKind::from_path(archive)
+        .ok_or_else(|| io::Error::other("not a supported archive"))?;
+    let file = BufReader::new(File::open(archive)?);
+    fs::create_dir_all(to)?;
+
+    let mut extractor = Extractor {
+        to,
+        remaining: max_size,
+        entries: 0,
+        stop,
+    };
+
+    match kind {
+        ArchiveKind::Zip => extract_zip(file, &mut extractor),
+        ArchiveKind::Tar => extract_tar(file, &mut extractor),
+        ArchiveKind::TarGz => extract_tar(GzDecoder::new(file), &mut extractor),
+    }
+}
+
+fn extract_zip(file: impl Read + Seek, extractor: &mut Extractor) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+
+        // S_IFLNK
+        if entry
+            .unix_mode()
+            .is_some_and(|mode| mode & 0o170000 == 0o120000)
+        {
+            continue;
+        }
+
+        if entry.is_dir() {
+            extractor.dir(&name)?;
+        } else {
+            extractor.file(&name, &mut entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_tar(file: impl Read, extractor: &mut Extractor) -> io::Result<()> {
+    let mut archive = tar::Archive::new(file);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_path_buf();
+        let kind = entry.header().entry_type();
+
+        if kind.is_dir() {
+            extractor.dir(&name)?;
+        } else if kind.is_file() {
+            extractor.file(&name, &mut entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the world in an extracted archive, the shallowest folder with a `level.dat`.
+pub fn find_world_root(dir: &Path) -> Option<PathBuf> {
+    find_root(dir, |dir| dir.join("level.dat").is_file())
+}
+
+/// Finds a BlueMap webapp in an extracted archive, the shallowest folder with a `settings.json`
+/// and an `index.html`.
+pub fn find_webapp_root(dir: &Path) -> Option<PathBuf> {
+    find_root(dir, |dir| {
+        dir.join("settings.json").is_file() && dir.join("index.html").is_file()
+    })
+}
+
+/// Shallowest folder under `dir` matching `is_root`, breadth first.
+fn find_root(dir: &Path, is_root: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+    let mut level = vec![dir.to_path_buf()];
+
+    for _ in 0..=ROOT_DEPTH {
+        let mut next = Vec::new();
+        level.sort();
+        for dir in level {
+            if is_root(&dir) {
+                return Some(dir);
+            }
+
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            next.extend(
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                    .map(|entry| entry.path()),
+            );
+        }
+        level = next;
+    }
+
+    None
+}
+
+/// Files under `dir` as paths relative to it, sorted so archives come out the same every time.
+pub fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut stack = vec![PathBuf::new()];
+
+    while let Some(relative) = stack.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            let kind = entry.file_type()?;
+            if kind.is_dir() {
+                stack.push(path);
+            } else if kind.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Writes `files`, relative to `dir`, into a new zip at `to`, blocking.
+///
+/// Files that are already gzipped, as BlueMap tiles are, are stored rather than compressed again.
+/// `progress` is called with the number of files written so far, writing fails once `stop` returns
+/// true.
+pub fn zip_files(
+    dir: &Path,
+    files: &[PathBuf],
+    to: &Path,
+    stop: &dyn Fn() -> bool,
+    progress: &dyn Fn(u64),
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(to)?));
+    let deflated = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let stored = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    for (i, file) in files.iter().enumerate() {
+        if stop() {
+            return Err(io::Error::other("archiving stopped"));
+        }
+
+        // zip entries always use forward slashes
+        let name = file
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let options = if file.extension().is_some_and(|ext| ext == "gz") {
+            stored
+        } else {
+            deflated
+        };
+
+        zip.start_file(name, options).map_err(io::Error::other)?;
+        io::copy(&mut File::open(dir.join(file))?, &mut zip)?;
+        progress(i as u64 + 1);
+    }
+
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// A fresh folder in the system temp folder, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("gmblue-archive-{}", fastrand::u64(..)));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Names of the entries directly in `dir`, sorted.
+    fn listing(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn tar_entry(builder: &mut tar::Builder<File>, name: &str, kind: tar::EntryType, data: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        // written as is, `set_path` refuses the names these tests need
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        if kind == tar::EntryType::Symlink || kind == tar::EntryType::Link {
+            header.set_link_name("/etc/passwd").unwrap();
+        }
+        header.set_cksum();
+        builder.append(&header, data).unwrap();
+    }
+
+    fn write_tar(path: &Path, entries: &[(&str, tar::EntryType, &[u8])]) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for (name, kind, data) in entries {
+            tar_entry(&mut builder, name, *kind, data);
+        }
+        builder.into_inner().unwrap().flush().unwrap();
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])], symlinks: &[&str]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        for name in symlinks {
+            zip.add_symlink(*name, "/etc/passwd", SimpleFileOptions::default())
+                .unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn extract(archive: &Path, to: &Path, max_size: u64) -> io::Result<()> {
+        extract_archive(archive, to, max_size, &|| false)
+    }
+
+    #[test]
+    fn entry_path_stays_inside() {
+        let to = Path::new("/out");
+        assert_eq!(
+            entry_path(to, Path::new("a/b")),
+            Some(PathBuf::from("/out/a/b"))
+        );
+        assert_eq!(
+            entry_path(to, Path::new("./a")),
+            Some(PathBuf::from("/out/a"))
+        );
+
+        for name in ["../x", "a/../../x", "a/../b", "/abs", "/out/a", ".", ""] {
+            assert_eq!(entry_path(to, Path::new(name)), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn zip_skips_escaping_entries_and_links() {
+        let dir = TempDir::new();
+        let (archive, out) = (dir.0.join("map.zip"), dir.0.join("out"));
+        write_zip(
+            &archive,
+            &[
+                ("../x", b"x"),
+                ("a/../../y", b"y"),
+                ("/abs", b"abs"),
+                ("ok/file.txt", b"ok"),
+            ],
+            &["link", "ok/link"],
+        );
+
+        extract(&archive, &out, 1024).unwrap();
+        assert_eq!(listing(&out), ["ok"]);
+        assert_eq!(listing(&out.join("ok")), ["file.txt"]);
+        assert_eq!(fs::read(out.join("ok/file.txt")).unwrap(), b"ok");
+        assert_eq!(listing(&dir.0), ["map.zip", "out"]);
+    }
+
+    #[test]
+    fn tar_skips_escaping_entries_and_links() {
+        use tar::EntryType::*;
+
+        let dir = TempDir::new();
+        let (archive, out) = (dir.0.join("map.tar"), dir.0.join("out"));
+        write_tar(
+            &archive,
+            &[
+                ("../x", Regular, b"x"),
+                ("a/../../y", Regular, b"y"),
+                ("/abs", Regular, b"abs"),
+                ("link", Symlink, b""),
+                ("hardlink", Link, b""),
+                ("ok", Directory, b""),
+                ("ok/file.txt", Regular, b"ok"),
+            ],
+        );
+
+        extract(&archive, &out, 1024).unwrap();
+        assert_eq!(listing(&out), ["ok"]);
+        assert_eq!(listing(&out.join("ok")), ["file.txt"]);
+        assert_eq!(fs::read(out.join("ok/file.txt")).unwrap(), b"ok");
+        assert_eq!(listing(&dir.0), ["map.tar", "out"]);
+    }
+
+    #[test]
+    fn max_size_counts_what_is_written() {
+        use tar::EntryType::*;
+
+        let dir = TempDir::new();
+        let tar = dir.0.join("map.tar");
+        write_tar(&tar, &[("a", Regular, &[0; 6]), ("b", Regular, &[0; 6])]);
+        assert!(extract(&tar, &dir.0.join("exact"), 12).is_ok());
+        assert!(extract(&tar, &dir.0.join("over"), 11).is_err());
+
+        let zip = dir.0.join("map.zip");
+        write_zip(&zip, &[("a", &[0; 11])], &[]);
+        assert!(extract(&zip, &dir.0.join("zip-exact"), 11).is_ok());
+        assert!(extract(&zip, &dir.0.join("zip-over"), 10).is_err());
+    }
+
+    #[test]
+    fn entry_limit() {
+        let dir = TempDir::new();
+        let mut extractor = Extractor {
+            to: &dir.0,
+            remaining: 0,
+            entries: MAX_ENTRIES - 1,
+            stop: &|| false,
+        };
+
+        assert!(extractor.dir(Path::new("last")).is_ok());
+        assert!(extractor.dir(Path::new("one-too-many")).is_err());
+        assert!(extractor.file(Path::new("file"), &mut io::empty()).is_err());
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let dir = TempDir::new();
+        let archive = dir.0.join("map.zip");
+        write_zip(&archive, &[("a", b"a")], &[]);
+
+        assert!(extract_archive(&archive, &dir.0.join("out"), 1024, &|| true).is_err());
+        assert!(!dir.0.join("out/a").exists());
+    }
+}
@@ -0,0 +1,24 @@
+use std::error::Error;
+
+use actix_web::{http::StatusCode, HttpResponse};
+use goodmorning_services::{bindings::services::v1::V1Response, functions::from_res};
+
+use crate::structs::{V1BlueError, V1BlueResponse};
+
+pub fn from_blue_res(res: Result<V1BlueResponse, Box<dyn Error>>) -> HttpResponse {
+    match res {
+        Ok(res) => HttpResponse::Ok().json(res),
+        Err(e) => blue_error(e),
+    }
+}
+
+/// Responds with a `V1BlueError` if that is what went wrong, anything else is handed to `from_res`.
+pub fn blue_error(e: Box<dyn Error>) -> HttpResponse {
+    match e.downcast::<V1BlueError>() {
+        Ok(kind) => HttpResponse::build(
+            StatusCode::from_u16(kind.status_code()).unwrap_or(StatusCode::BAD_REQUEST),
+        )
+        .json(V1BlueResponse::Error { kind: *kind }),
+        Err(e) => from_res::<V1Response>(Err(e)),
+    }
+}
@@ -0,0 +1,23 @@
+/// Reads a top level `key: value` (or `key = value`) entry from a BlueMap HOCON config.
+///
+/// Only handles the flat single-line entries presets are made of, quotes around the value are
+/// stripped.
+pub fn conf_value(conf: &str, key: &str) -> Option<String> {
+    conf.lines().rev().find_map(|line| {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with("//") {
+            return None;
+        }
+
+        let (k, v) = line.split_once([':', '='])?;
+        if k.trim().trim_matches('"') != key {
+            return None;
+        }
+
+        let v = v.trim();
+        Some(match v.strip_prefix('"') {
+            Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+            None => v.split('#').next().unwrap_or_default().trim().to_string(),
+        })
+    })
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use tokio::fs;
+
+/// Recursively copies the contents of `from` into `to`, creating `to` if needed.
+pub async fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut stack = vec![(from.to_path_buf(), to.to_path_buf())];
+
+    while let Some((from, to)) = stack.pop() {
+        fs::create_dir_all(&to).await?;
+        let mut entries = fs::read_dir(&from).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                stack.push((entry.path(), to.join(entry.file_name())));
+            } else {
+                fs::copy(entry.path(), to.join(entry.file_name())).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors `from` into `to` like `copy_dir`, but hard-links map tiles instead of copying them.
+///
+/// BlueMap writes a tile to a temporary file and moves it over the old one, so a tile it renders
+/// again never changes the linked original. Everything else is copied, it is small and may be
+/// rewritten in place. Falls back to copying where links are not possible, e.g. across devices.
+pub async fn link_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut stack = vec![(from.to_path_buf(), to.to_path_buf(), false)];
+
+    while let Some((from, to, tiles)) = stack.pop() {
+        fs::create_dir_all(&to).await?;
+        let mut entries = fs::read_dir(&from).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let target = to.join(entry.file_name());
+            if entry.file_type().await?.is_dir() {
+                stack.push((entry.path(), target, tiles || entry.file_name() == "tiles"));
+            } else if !tiles || fs::hard_link(entry.path(), &target).await.is_err() {
+                fs::copy(entry.path(), &target).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Total size in bytes of the files under `dir`, 0 if it does not exist.
+pub async fn dir_size(dir: &Path) -> u64 {
+    let mut size = 0;
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+
+    size
+}
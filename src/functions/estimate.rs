@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+
+use goodmorning_services::{
+    functions::get_user_dir,
+    structs::{Account, GMServices},
+};
+
+use crate::{
+    structs::{
+        DimensionEstimate, RenderArea, RenderEstimate, RenderManifest, RenderRates, RenderTask,
+    },
+    values::VALUES,
+};
+
+use super::{
+    check_world, count_changed_regions, count_chunks, count_regions, dir_size, now,
+    preset_dimension, preset_path, region_dir, snapshots_size, ArchiveKind,
+};
+
+/// Seconds `storage_used` is reused for, the render page estimates on every change to its form.
+const STORAGE_USED_TTL: u64 = 30;
+
+/// Last `storage_used` of each account, with when it was counted.
+static STORAGE_USED: LazyLock<Mutex<HashMap<i64, (u64, u64)>>> = LazyLock::new(Default::default);
+
+/// Counts what a render of `world` would go through and predicts its duration and size.
+///
+/// With `since`, only regions changed after it are counted, as an update would. The quota is
+/// the one of `account`'s limit tier.
+pub async fn estimate_render(
+    account: &Account,
+    world: &Path,
+    dimensions: &[String],
+    area: &RenderArea,
+    since: Option<u64>,
+) -> RenderEstimate {
+    let mut estimates = Vec::new();
+    for dimension in dimensions {
+        let dir = region_dir(world, dimension);
+        let regions = match since {
+            Some(since) => count_changed_regions(&dir, since, area).await,
+            None => count_regions(&dir, area).await,
+        };
+        estimates.push(DimensionEstimate {
+            dimension: dimension.clone(),
+            regions,
+            chunks: count_chunks(&dir, area, since).await,
+        });
+    }
+
+    let regions = estimates
+        .iter()
+        .map(|dimension| dimension.regions)
+        .sum::<u64>();
+    let chunks = estimates.iter().map(|dimension| dimension.chunks).sum();
+    let rates = RenderRates::recent().await;
+    let seconds = (regions as f64 * rates.seconds_per_region).ceil() as u64;
+    let size = (regions as f64 * rates.bytes_per_region).ceil() as u64;
+
+    let values = VALUES.get();
+    let config = &values.config;
+    let (quota, used) = match config.storage_quota(&account.limit) {
+        Some(quota) => (Some(quota), Some(storage_used(account.id).await)),
+        None => (None, None),
+    };
+
+    RenderEstimate {
+        dimensions: estimates,
+        regions,
+        chunks,
+        seconds,
+        size,
+        rates,
+        timeout: config.render_timeout,
+        exceeds_timeout: seconds > config.render_timeout,
+        quota,
+        used,
+        exceeds_quota: quota.is_some_and(|quota| used.unwrap_or(0) + size > quota),
+    }
+}
+
+/// Bytes `user` has stored, their snapshots included.
+///
+/// Counted at most once every `STORAGE_USED_TTL` seconds per account, walking a large account's
+/// directory takes a while.
+pub async fn storage_used(user: i64) -> u64 {
+    if let Some((counted, used)) = STORAGE_USED.lock().unwrap().get(&user) {
+        if now() < counted + STORAGE_USED_TTL {
+            return *used;
+        }
+    }
+
+    let used = dir_size(&get_user_dir(user, None)).await + snapshots_size(user).await;
+    let mut cache = STORAGE_USED.lock().unwrap();
+    let now = now();
+    cache.retain(|_, (counted, _)| now < *counted + STORAGE_USED_TTL);
+    cache.insert(user, (now, used));
+    used
+}
+
+/// Checks a render nobody is there to start, from a watch or a schedule, as `/render` would: the
+/// account must still be verified and have Blue, the source must be a world, and the render must
+/// not be expected to go over the storage quota, as nobody sees the estimate's warning.
+pub async fn check_unattended(account: &Account, task: &RenderTask) -> Result<(), String> {
+    account
+        .clone()
+        .v1_restrict_verified()
+        .and_then(|account| account.v1_contains(&GMServices::Blue))
+        .map_err(|e| e.to_string())?;
+
+    let from_abs = get_user_dir(account.id, None).join(&task.from);
+    let quota = VALUES.get().config.storage_quota(&account.limit);
+    if ArchiveKind::from_path(&from_abs).is_some() {
+        // archives are only estimated once extracted, so only a full quota stops them
+        if let Some(quota) = quota {
+            if storage_used(account.id).await >= quota {
+                return Err("storage quota used up".to_string());
+            }
+        }
+        return Ok(());
+    }
+
+    let dimensions = if task.dimensions.is_empty() {
+        vec![preset_dimension(&preset_path(account.id, &task.preset)).await]
+    } else {
+        task.dimensions.clone()
+    };
+    check_world(&from_abs, &dimensions)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if quota.is_none() {
+        return Ok(());
+    }
+
+    let since = if task.update {
+        RenderManifest::load(&get_user_dir(account.id, None).join(&task.to))
+            .await
+            .filter(|manifest| manifest.renders_like(task))
+            .map(|manifest| manifest.started)
+    } else {
+        None
+    };
+    let estimate = estimate_render(account, &from_abs, &dimensions, &task.area, since).await;
+    if estimate.exceeds_quota {
+        return Err(format!(
+            "likely to go over the storage quota, {} of {} bytes used",
+            estimate.used.unwrap_or_default(),
+            estimate.quota.unwrap_or_default()
+        ));
+    }
+
+    Ok(())
+}
@@ -4,7 +4,7 @@ use goodmorning_services::bindings::{services::v1::V1Error, traits::ErrorTrait};
 
 use std::{error::Error, path::Path};
 
-use crate::values::BLUE_CONFIG;
+use crate::values::VALUES;
 
 use super::internalserver_error;
 
@@ -24,7 +24,7 @@ pub async fn from_res(
 
     let path = match v1e {
         V1Error::InvalidToken => {
-            Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/been-loggedout.html")
+            Path::new(&VALUES.get().config.static_path).join("html/been-loggedout.html")
         }
         _ => return internalserver_error(err),
     };
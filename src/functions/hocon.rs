@@ -0,0 +1,236 @@
+/// A value in a preset, as far as GM Blue reads presets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HoconValue {
+    /// A quoted string, unescaped.
+    Quoted(String),
+    /// An unquoted word, numbers and booleans included.
+    Unquoted(String),
+    Object(Vec<(String, HoconValue)>),
+    Array(Vec<HoconValue>),
+}
+
+/// Parses the subset of HOCON presets are written in into its top level entries, in order.
+///
+/// Anything that could pull in more than the file itself says is an error rather than being
+/// resolved: includes, substitutions, `+=`, triple quoted strings and value concatenation. A root
+/// object in braces is not accepted either, entries are appended to presets.
+pub fn parse_hocon(content: &str) -> Result<Vec<(String, HoconValue)>, String> {
+    let mut parser = Parser {
+        chars: content.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+
+    parser.entries(None)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, reason: &str) -> Result<T, String> {
+        Err(format!("line {}: {reason}", self.line))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips spaces and comments, and newlines too if `newlines`.
+    fn skip(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            if c == '#' || self.starts_with("//") {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c == '\n' && !newlines {
+                return;
+            } else if c.is_whitespace() || c == '\u{feff}' {
+                self.bump();
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Entries up to `close`, or to the end of the input for the root.
+    fn entries(&mut self, close: Option<char>) -> Result<Vec<(String, HoconValue)>, String> {
+        let mut entries = Vec::new();
+
+        loop {
+            self.skip(true);
+            while self.peek() == Some(',') {
+                self.bump();
+                self.skip(true);
+            }
+
+            match self.peek() {
+                None if close.is_none() => return Ok(entries),
+                None => return self.error("unclosed {"),
+                Some(c) if Some(c) == close => {
+                    self.bump();
+                    return Ok(entries);
+                }
+                Some('{') if close.is_none() && entries.is_empty() => {
+                    return self.error("wrap-around braces are not allowed")
+                }
+                _ => {}
+            }
+
+            let key = self.key()?;
+            self.skip(false);
+
+            let value = match self.peek() {
+                Some('{') => self.value()?,
+                Some(':' | '=') => {
+                    self.bump();
+                    self.skip(false);
+                    self.value()?
+                }
+                Some('+') if self.starts_with("+=") => return self.error("+= is not allowed"),
+                _ if key == "include" => return self.error("includes are not allowed"),
+                _ => return self.error(&format!("expected : after {key}")),
+            };
+
+            self.end_of_value(close)?;
+            entries.push((key, value));
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        if self.peek() == Some('"') {
+            return self.quoted();
+        }
+
+        let mut key = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            key.push(c);
+            self.bump();
+        }
+
+        if key.is_empty() {
+            return self.error("expected a key");
+        }
+        Ok(key)
+    }
+
+    fn value(&mut self) -> Result<HoconValue, String> {
+        match self.peek() {
+            Some('{') => {
+                self.bump();
+                Ok(HoconValue::Object(self.entries(Some('}'))?))
+            }
+            Some('[') => {
+                self.bump();
+                self.array()
+            }
+            Some('"') if self.starts_with("\"\"\"") => {
+                self.error("triple quoted strings are not allowed")
+            }
+            Some('"') => Ok(HoconValue::Quoted(self.quoted()?)),
+            Some('$') => self.error("substitutions are not allowed"),
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+                {
+                    word.push(c);
+                    self.bump();
+                }
+
+                if word.is_empty() {
+                    return self.error("expected a value");
+                }
+                Ok(HoconValue::Unquoted(word))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<HoconValue, String> {
+        let mut values = Vec::new();
+
+        loop {
+            self.skip(true);
+            while self.peek() == Some(',') {
+                self.bump();
+                self.skip(true);
+            }
+
+            match self.peek() {
+                None => return self.error("unclosed ["),
+                Some(']') => {
+                    self.bump();
+                    return Ok(HoconValue::Array(values));
+                }
+                _ => {}
+            }
+
+            values.push(self.value()?);
+            self.end_of_value(Some(']'))?;
+        }
+    }
+
+    /// After a value only a separator or the closing bracket may follow, `a: x y` or `a: "x" "y"`
+    /// would be concatenated.
+    fn end_of_value(&mut self, close: Option<char>) -> Result<(), String> {
+        self.skip(false);
+        match self.peek() {
+            None | Some('\n' | ',') => Ok(()),
+            Some(c) if Some(c) == close => Ok(()),
+            Some(_) => self.error("one value per key, separated by a new line or a comma"),
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        self.bump();
+        let mut s = String::new();
+
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unclosed string"),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex = (0..4).filter_map(|_| self.bump()).collect::<String>();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => s.push(c),
+                            None => return self.error("invalid \\u escape"),
+                        }
+                    }
+                    _ => return self.error("invalid escape"),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+}
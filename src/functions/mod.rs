@@ -4,3 +4,37 @@ mod from_res;
 pub use from_res::*;
 mod nonce;
 pub use nonce::*;
+mod time;
+pub use time::*;
+mod blue_res;
+pub use blue_res::*;
+mod conf;
+pub use conf::*;
+mod world;
+pub use world::*;
+mod copy;
+pub use copy::*;
+mod preset;
+pub use preset::*;
+mod webapp;
+pub use webapp::*;
+mod overrides;
+pub use overrides::*;
+mod hocon;
+pub use hocon::*;
+mod estimate;
+pub use estimate::*;
+mod archive;
+pub use archive::*;
+mod snapshot;
+pub use snapshot::*;
+mod watch;
+pub use watch::*;
+mod account;
+pub use account::*;
+mod schedule;
+pub use schedule::*;
+mod resume;
+pub use resume::*;
+mod queue;
+pub use queue::*;
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::structs::V1BlueError;
+
+/// Values a preset override accepts.
+#[derive(Clone, Copy, Debug)]
+pub enum OverrideKind {
+    Bool,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Number {
+        min: f64,
+        max: f64,
+    },
+    /// `#rrggbb`
+    Color,
+}
+
+/// Preset settings that can be overridden for a single render, everything else is rejected.
+pub const PRESET_OVERRIDES: &[(&str, OverrideKind)] = &[
+    ("render-edges", OverrideKind::Bool),
+    (
+        "edge-light-strength",
+        OverrideKind::Integer { min: 0, max: 15 },
+    ),
+    ("ambient-light", OverrideKind::Number { min: 0., max: 1. }),
+    ("sky-light", OverrideKind::Number { min: 0., max: 1. }),
+    ("sky-color", OverrideKind::Color),
+    ("void-color", OverrideKind::Color),
+    (
+        "remove-caves-below-y",
+        OverrideKind::Integer {
+            min: -10000,
+            max: 10000,
+        },
+    ),
+    (
+        "cave-detection-ocean-floor",
+        OverrideKind::Integer {
+            min: -10000,
+            max: 10000,
+        },
+    ),
+    ("cave-detection-uses-block-light", OverrideKind::Bool),
+    (
+        "min-inhabited-time",
+        OverrideKind::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+    ),
+    ("ignore-missing-light-data", OverrideKind::Bool),
+    ("save-hires-layer", OverrideKind::Bool),
+    ("enable-perspective-view", OverrideKind::Bool),
+    ("enable-flat-view", OverrideKind::Bool),
+    ("enable-free-flight-view", OverrideKind::Bool),
+];
+
+/// Checks overrides against `PRESET_OVERRIDES` and turns them into preset lines, which win over
+/// the preset's own values when appended to it.
+pub fn override_lines(overrides: &BTreeMap<String, Value>) -> Result<String, V1BlueError> {
+    let mut lines = String::new();
+
+    for (key, value) in overrides.iter() {
+        let kind = override_kind(key).ok_or_else(|| V1BlueError::InvalidOverride {
+            key: key.clone(),
+            reason: "cannot be overridden".to_string(),
+        })?;
+        let value = override_value(kind, value).map_err(|reason| V1BlueError::InvalidOverride {
+            key: key.clone(),
+            reason,
+        })?;
+
+        lines.push_str(&format!("{key}: {value}\n"));
+    }
+
+    Ok(lines)
+}
+
+pub fn override_kind(key: &str) -> Option<OverrideKind> {
+    PRESET_OVERRIDES
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, kind)| *kind)
+}
+
+/// Checks a value against what `kind` accepts and writes it as it goes in a preset.
+pub fn override_value(kind: OverrideKind, value: &Value) -> Result<String, String> {
+    let invalid = |reason: &str| reason.to_string();
+
+    let value = match kind {
+        OverrideKind::Bool => value
+            .as_bool()
+            .ok_or_else(|| invalid("expected true or false"))?
+            .to_string(),
+        OverrideKind::Integer { min, max } => {
+            let value = value
+                .as_i64()
+                .ok_or_else(|| invalid("expected a whole number"))?;
+            if !(min..=max).contains(&value) {
+                return Err(invalid(&format!("must be between {min} and {max}")));
+            }
+            value.to_string()
+        }
+        OverrideKind::Number { min, max } => {
+            let value = value.as_f64().ok_or_else(|| invalid("expected a number"))?;
+            if !(min..=max).contains(&value) {
+                return Err(invalid(&format!("must be between {min} and {max}")));
+            }
+            value.to_string()
+        }
+        OverrideKind::Color => {
+            let value = value
+                .as_str()
+                .filter(|color| {
+                    color.len() == 7
+                        && color.starts_with('#')
+                        && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+                })
+                .ok_or_else(|| invalid("expected a colour such as #7dabff"))?;
+            format!("\"{value}\"")
+        }
+    };
+
+    Ok(value)
+}
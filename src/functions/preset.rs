@@ -0,0 +1,258 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use bluemap_singleserve::{Config, MasterConfig};
+use goodmorning_services::{functions::get_usersys_dir, structs::GMServices};
+use tokio::fs;
+
+use serde_json::Value;
+
+use super::{
+    conf_value, override_kind, override_value, parse_hocon, valid_dimension, HoconValue, OVERWORLD,
+};
+use crate::structs::{PresetInfo, PresetSidecar, V1BlueError, PREVIEW_EXTENSIONS};
+
+/// Presets starting with this are looked up in the user's own presets directory.
+pub const USER_PRESET_PREFIX: &str = "user/";
+pub const USER_PRESET_LIMIT: usize = 50;
+pub const USER_PRESET_MAX_SIZE: usize = 64 * 1024;
+
+/// Dimension a preset renders, the overworld if it does not say.
+pub async fn preset_dimension(preset: &Path) -> String {
+    fs::read_to_string(preset)
+        .await
+        .ok()
+        .and_then(|conf| conf_value(&conf, "dimension"))
+        .unwrap_or(OVERWORLD.to_string())
+}
+
+pub fn user_presets_dir(user: i64) -> PathBuf {
+    get_usersys_dir(user, Some(GMServices::Blue)).join("presets")
+}
+
+/// Where a preset as passed to the render endpoint lives on disk.
+pub fn preset_path(user: i64, preset: &str) -> PathBuf {
+    match preset.strip_prefix(USER_PRESET_PREFIX) {
+        Some(name) => user_presets_dir(user).join(name),
+        None => MasterConfig::get().templates.join(preset),
+    }
+}
+
+/// Preview image of a global preset, an image with the same stem next to it.
+pub fn preset_preview(name: &str) -> Option<PathBuf> {
+    let templates = &MasterConfig::get().templates;
+    let stem = Path::new(name).file_stem()?;
+
+    PREVIEW_EXTENSIONS
+        .iter()
+        .map(|ext| templates.join(stem).with_extension(ext))
+        .find(|path| path.is_file())
+}
+
+/// Metadata of a global preset, read from the templates directory.
+pub fn global_preset_info(name: &str) -> PresetInfo {
+    let templates = &MasterConfig::get().templates;
+    let sidecar = std::fs::read(templates.join(name).with_extension("json"))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default();
+
+    PresetInfo::new(
+        name.to_string(),
+        &std::fs::read_to_string(templates.join(name)).unwrap_or_default(),
+        sidecar,
+        preset_preview(name).map(|_| format!("/api/blue/v1/presetpreview/{name}")),
+    )
+}
+
+pub async fn user_preset_info(user: i64, name: &str) -> PresetInfo {
+    PresetInfo::new(
+        format!("{USER_PRESET_PREFIX}{name}"),
+        &fs::read_to_string(user_presets_dir(user).join(name))
+            .await
+            .unwrap_or_default(),
+        PresetSidecar::default(),
+        None,
+    )
+}
+
+/// File names of a user's presets, sorted.
+pub async fn user_presets(user: i64) -> Vec<String> {
+    let mut presets = Vec::new();
+    let Ok(mut entries) = fs::read_dir(user_presets_dir(user)).await else {
+        return presets;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.path().extension() == Some(OsStr::new("conf")) {
+            presets.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    presets.sort();
+    presets
+}
+
+pub fn validate_preset_name(name: &str) -> Result<(), V1BlueError> {
+    if name.len() > 64
+        || name.starts_with('.')
+        || !name.ends_with(".conf")
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+    {
+        return Err(V1BlueError::InvalidPreset {
+            reason:
+                "names may only contain letters, digits, '.', '-' and '_', and must end with .conf"
+                    .to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Settings a user preset may have besides `PRESET_OVERRIDES`.
+///
+/// Everything else is rejected, keys that take a path such as `world`, `storage` or
+/// `resource-packs` most of all, BlueMap would resolve them outside the user's directory.
+const USER_PRESET_KEYS: &[(&str, UserPresetKey)] = &[
+    ("name", UserPresetKey::Text),
+    ("dimension", UserPresetKey::Dimension),
+    ("sorting", UserPresetKey::Integer),
+    ("min-x", UserPresetKey::Integer),
+    ("max-x", UserPresetKey::Integer),
+    ("min-z", UserPresetKey::Integer),
+    ("max-z", UserPresetKey::Integer),
+    ("min-y", UserPresetKey::Integer),
+    ("max-y", UserPresetKey::Integer),
+];
+/// Longest `name` a user preset may set.
+const USER_PRESET_NAME_MAX: usize = 100;
+
+#[derive(Clone, Copy)]
+enum UserPresetKey {
+    Text,
+    Dimension,
+    Integer,
+}
+
+/// Checks a user preset only sets what `PRESET_OVERRIDES` and `USER_PRESET_KEYS` allow, with
+/// values they accept.
+pub fn validate_preset(content: &str) -> Result<(), V1BlueError> {
+    let invalid = |reason: String| V1BlueError::InvalidPreset { reason };
+
+    if content.len() > USER_PRESET_MAX_SIZE {
+        return Err(invalid(format!(
+            "presets are limited to {USER_PRESET_MAX_SIZE} bytes"
+        )));
+    }
+
+    for (key, value) in parse_hocon(content).map_err(invalid)? {
+        let text = match &value {
+            HoconValue::Quoted(text) | HoconValue::Unquoted(text) => text,
+            HoconValue::Object(_) | HoconValue::Array(_) => {
+                return Err(invalid(format!("{key} cannot be set in your own presets")))
+            }
+        };
+
+        if let Some(kind) = override_kind(&key) {
+            // the same values a render's overrides accept
+            let value = match value {
+                HoconValue::Quoted(_) => Value::String(text.clone()),
+                _ => serde_json::from_str(text).unwrap_or(Value::String(text.clone())),
+            };
+            override_value(kind, &value).map_err(|reason| invalid(format!("{key}: {reason}")))?;
+            continue;
+        }
+
+        let Some((_, kind)) = USER_PRESET_KEYS.iter().find(|(name, _)| *name == key) else {
+            return Err(invalid(format!("{key} cannot be set in your own presets")));
+        };
+
+        match kind {
+            UserPresetKey::Text if text.chars().count() > USER_PRESET_NAME_MAX => {
+                return Err(invalid(format!(
+                    "{key} is limited to {USER_PRESET_NAME_MAX} characters"
+                )))
+            }
+            UserPresetKey::Dimension if !valid_dimension(text) => {
+                return Err(invalid(format!(
+                    "{key}: {text} is not a dimension id such as minecraft:the_nether"
+                )))
+            }
+            UserPresetKey::Integer if text.parse::<i32>().is_err() => {
+                return Err(invalid(format!("{key}: expected a whole number")))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_preset;
+
+    #[test]
+    fn accepts_whitelisted_keys() {
+        let preset = r##"
+# a comment
+// another comment
+name: "My map"
+dimension: "minecraft:the_nether"
+sorting = 2
+render-edges: true
+ambient-light: 0.5
+sky-color: "#7dabff"
+min-y: -64, max-y: 320
+"##;
+        assert!(validate_preset(preset).is_ok());
+    }
+
+    #[test]
+    fn rejects_path_keys() {
+        for preset in [
+            r#"world: "/etc""#,
+            r#"root: "x""#,
+            r#"storage: "file""#,
+            r#"storage { type: FILE, root: "/etc" }"#,
+            r#"root: ["/etc"]"#,
+            r#"a: "x", root: "/etc""#,
+        ] {
+            assert!(validate_preset(preset).is_err(), "{preset}");
+        }
+    }
+
+    #[test]
+    fn rejects_includes_and_substitutions() {
+        for preset in [
+            r#"include "/etc/x""#,
+            r#"a { include "/etc/x" }"#,
+            "name: ${HOME}",
+            r#"name: """x""""#,
+            "resource-packs += \"x\"",
+        ] {
+            assert!(validate_preset(preset).is_err(), "{preset}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for preset in [
+            r#"name: "x" "y""#,
+            "name: x y",
+            r#"name: "unclosed"#,
+            r#"dimension: "minecraft:../../x""#,
+            "dimension: \"minecraft:x\\\"\\nworld: \\\"/etc\"",
+            "render-edges: maybe",
+            "sky-color: red",
+            "min-y: high",
+            "{ name: x }",
+        ] {
+            assert!(validate_preset(preset).is_err(), "{preset}");
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use goodmorning_services::{functions::get_user_dir, structs::Account};
+
+use crate::{
+    structs::{BlueTask, QueueEstimate, QueuedJob, RenderHistory, RenderManifest, V1BlueError},
+    values::{RENDER_JOBS, VALUES},
+};
+
+use super::{
+    count_changed_regions, count_regions, now, preset_dimension, preset_path, queue_limits,
+    region_dir, ArchiveKind,
+};
+
+/// Works out where one of `account`'s jobs stands in the queue and when it should finish.
+///
+/// Running jobs free their slots after their expected duration, or sooner going by the tiles they
+/// have written, and each waiting job takes the first slot to free up in the order `RenderSlots`
+/// would start them.
+pub async fn queue_estimate(id: u64, account: &Account) -> Result<QueueEstimate, V1BlueError> {
+    let renders = RENDER_JOBS.get().unwrap();
+    let (status, _) = renders
+        .status(id, account.id)
+        .ok_or(V1BlueError::JobNotFound)?;
+    if status.is_finished() {
+        return Err(V1BlueError::JobFinished);
+    }
+
+    // without a server-wide cap, a job only ever waits for its owner's other jobs
+    let (slots, shared) = match VALUES.get().config.max_renders {
+        Some(max) => (max, true),
+        None => (queue_limits(&account.limit).0, false),
+    };
+    let in_the_way = |owner: i64| shared || owner == account.id;
+
+    let history = RenderHistory::recent().await;
+    let running = renders
+        .running()
+        .into_iter()
+        .filter(|job| in_the_way(job.owner))
+        .collect::<Vec<_>>();
+
+    let mut free = BinaryHeap::new();
+    for job in running.iter() {
+        let remaining = remaining(job, duration(job, &history).await);
+        if job.id == id {
+            return Ok(QueueEstimate {
+                position: None,
+                ahead: 0,
+                running: running.len(),
+                slots,
+                starts_in: 0,
+                finishes_in: remaining,
+            });
+        }
+        free.push(Reverse(remaining));
+    }
+
+    // a lowered cap can leave more jobs running than there are slots
+    while free.len() > slots {
+        free.pop();
+    }
+    while free.len() < slots {
+        free.push(Reverse(0));
+    }
+
+    let order = renders
+        .slots
+        .order()
+        .into_iter()
+        .filter(|(_, owner)| in_the_way(*owner))
+        .map(|(job, _)| job)
+        .collect::<Vec<_>>();
+    // a job that has not reached `RenderSlots` yet goes after everything waiting
+    let ahead = order
+        .iter()
+        .position(|job| *job == id)
+        .unwrap_or(order.len());
+
+    for job in order[..ahead].iter() {
+        let Reverse(start) = free.pop().unwrap_or_default();
+        let duration = match renders.queued(*job) {
+            Some(job) => duration(&job, &history).await,
+            None => 0,
+        };
+        free.push(Reverse(start + duration));
+    }
+
+    let Reverse(starts_in) = free.pop().unwrap_or_default();
+    let job = renders.queued(id).ok_or(V1BlueError::JobNotFound)?;
+
+    Ok(QueueEstimate {
+        position: Some(ahead + 1),
+        ahead,
+        running: running.len(),
+        slots,
+        starts_in,
+        finishes_in: starts_in + duration(&job, &history).await,
+    })
+}
+
+/// Expected seconds a job takes from start to finish.
+async fn duration(job: &QueuedJob, history: &RenderHistory) -> u64 {
+    match job.task {
+        BlueTask::Render(_) => history.seconds(regions(job).await),
+        // archiving and copying are quick next to rendering, they only hold up the queue briefly
+        BlueTask::Export(_) | BlueTask::Import(_) => 0,
+    }
+}
+
+/// Expected seconds left of a running job.
+fn remaining(job: &QueuedJob, duration: u64) -> u64 {
+    let progress = &job.progress;
+    let elapsed = progress
+        .started
+        .map(|started| now().saturating_sub(started))
+        .unwrap_or_default();
+
+    if progress.done > 0 && progress.total > 0 {
+        elapsed * progress.total.saturating_sub(progress.done) / progress.done
+    } else {
+        duration.saturating_sub(elapsed)
+    }
+}
+
+/// Regions a render goes through, counted once and kept on the job.
+///
+/// `None` for archives, which are only looked into once they are extracted.
+async fn regions(job: &QueuedJob) -> Option<u64> {
+    if job.regions.is_some() {
+        return job.regions;
+    }
+
+    let BlueTask::Render(task) = &job.task else {
+        return None;
+    };
+
+    let world = get_user_dir(task.user, None).join(&task.from);
+    if ArchiveKind::from_path(&world).is_some() {
+        return None;
+    }
+
+    let dimensions = if task.dimensions.is_empty() {
+        vec![preset_dimension(&preset_path(task.user, &task.preset)).await]
+    } else {
+        task.dimensions.clone()
+    };
+    let since = if task.update {
+        RenderManifest::load(&get_user_dir(task.user, None).join(&task.to))
+            .await
+            .map(|manifest| manifest.started)
+    } else {
+        None
+    };
+
+    let mut regions = 0;
+    for dimension in dimensions.iter() {
+        let dir = region_dir(&world, dimension);
+        regions += match since {
+            Some(since) => count_changed_regions(&dir, since, &task.area).await,
+            None => count_regions(&dir, &task.area).await,
+        };
+    }
+
+    RENDER_JOBS.get().unwrap().set_regions(job.id, regions);
+    Some(regions)
+}
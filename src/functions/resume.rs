@@ -0,0 +1,133 @@
+use std::{error::Error, path::Path};
+
+use actix_web::web::Data;
+use goodmorning_services::{
+    functions::{get_user_dir, get_usersys_dir},
+    structs::{GMServices, Jobs},
+};
+use mongodb::bson::doc;
+use tokio::fs;
+use tokio_stream::StreamExt;
+
+use crate::{
+    structs::{BlueTask, RenderStatus, FINISHED_RETENTION},
+    values::{JOB_RECORDS, RENDER_JOBS, VALUES},
+};
+
+use super::{account_by_id, now};
+
+/// Loads the jobs stored before the last shutdown and starts storing new ones.
+///
+/// Queued jobs are queued again, running ones were interrupted and are retried up to
+/// `interrupted_retries` times before being marked failed. Recently finished jobs are loaded too,
+/// so their status can still be queried.
+pub async fn resume_jobs(jobs: Data<Jobs>) -> Result<(), Box<dyn Error>> {
+    let collection = JOB_RECORDS.get().unwrap();
+    let renders = RENDER_JOBS.get().unwrap();
+
+    collection
+        .delete_many(
+            doc! { "finished": { "$lt": now().saturating_sub(FINISHED_RETENTION) as i64 } },
+        )
+        .await?;
+
+    let mut records = Vec::new();
+    let mut cursor = collection.find(doc! {}).await?;
+    while let Some(record) = cursor.next().await {
+        match record {
+            Ok(record) => records.push(record),
+            Err(e) => log::warn!("skipping unreadable job record: {e}"),
+        }
+    }
+
+    renders.store_in(collection.clone());
+
+    let mut resumed = 0;
+    for mut record in records {
+        if record.status.is_finished() {
+            renders.restore(record);
+            continue;
+        }
+
+        if matches!(record.status, RenderStatus::Running) {
+            let target = match &record.task {
+                BlueTask::Render(task) => Some(get_user_dir(record.owner, None).join(&task.to)),
+                BlueTask::Export(_) | BlueTask::Import(_) => None,
+            };
+            clean_staging(record.owner, record.id, target.as_deref()).await;
+
+            if record.attempts >= VALUES.get().config.interrupted_retries {
+                record.finished = Some(now());
+                record.status = RenderStatus::Failed {
+                    error: serde_json::Value::String("interrupted by a server restart".to_string()),
+                };
+                renders.restore(record);
+                continue;
+            }
+
+            record.attempts += 1;
+            record.status = RenderStatus::Queued;
+        }
+
+        let Some(account) = account_by_id(record.owner).await? else {
+            record.finished = Some(now());
+            record.status = RenderStatus::Cancelled;
+            renders.restore(record);
+            continue;
+        };
+
+        let (id, task) = (record.id, record.task.clone());
+        renders.restore(record);
+        renders.log(id, "Queued again after a server restart");
+        resumed += 1;
+
+        let jobs = jobs.clone();
+        actix_web::rt::spawn(async move {
+            let renders = RENDER_JOBS.get().unwrap();
+            let _ = match task {
+                BlueTask::Render(task) => renders.run(&jobs, &account, task).await,
+                BlueTask::Export(task) => renders.run(&jobs, &account, task).await,
+                BlueTask::Import(task) => renders.run(&jobs, &account, task).await,
+            };
+        });
+    }
+
+    if resumed > 0 {
+        log::info!("queued {resumed} jobs again after restart");
+    }
+    Ok(())
+}
+
+/// Removes what an interrupted job left in the staging folder, all named after its id.
+///
+/// A render interrupted while replacing its map leaves the old map as `{job}.old`, it is put back
+/// at `target` if the new one never made it there.
+async fn clean_staging(user: i64, job: u64, target: Option<&Path>) {
+    let staging = get_usersys_dir(user, Some(GMServices::Blue)).join("staging");
+    let Ok(mut entries) = fs::read_dir(&staging).await else {
+        return;
+    };
+
+    let prefix = job.to_string();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == format!("{prefix}.old") {
+            if let Some(target) = target {
+                if !fs::try_exists(target).await.unwrap_or(true) {
+                    match fs::rename(entry.path(), target).await {
+                        Ok(()) => {
+                            log::info!("restored the map job {job} was replacing");
+                            continue;
+                        }
+                        Err(e) => {
+                            log::warn!("failed to restore the map job {job} was replacing: {e}")
+                        }
+                    }
+                }
+            }
+            let _ = fs::remove_dir_all(entry.path()).await;
+        } else if name == prefix || name.starts_with(&format!("{prefix}-")) {
+            let _ = fs::remove_dir_all(entry.path()).await;
+        }
+    }
+}
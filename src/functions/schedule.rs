@@ -0,0 +1,159 @@
+use std::{error::Error, time::Duration};
+
+use actix_web::web::Data;
+use goodmorning_services::{functions::get_user_dir, structs::Jobs};
+use tokio::fs;
+
+use crate::{
+    structs::{RenderManifest, RenderSchedule, RenderStatus, RenderTask, ScheduleRun},
+    values::{RENDER_JOBS, VALUES},
+};
+
+use super::{account_by_id, check_unattended, map_exists, now};
+
+/// Seconds between checks for due schedules, cron expressions are only as precise as a minute.
+const SCHEDULE_INTERVAL: u64 = 20;
+
+/// Queues the render of every schedule that is due, for as long as the server runs.
+pub async fn run_schedules(jobs: Data<Jobs>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(SCHEDULE_INTERVAL)).await;
+
+        let schedules = match RenderSchedule::due(now()).await {
+            Ok(schedules) => schedules,
+            Err(e) => {
+                log::error!("failed to load due schedules: {e}");
+                continue;
+            }
+        };
+
+        for schedule in schedules {
+            let id = schedule.id;
+            if let Err(e) = run_schedule(&jobs, schedule).await {
+                log::warn!("failed to run schedule {id}: {e}");
+            }
+        }
+    }
+}
+
+/// The render a schedule runs, an update if the target has already been rendered.
+async fn schedule_task(
+    schedule: &RenderSchedule,
+    keep_versions: usize,
+) -> Result<RenderTask, String> {
+    let to_abs = get_user_dir(schedule.user, None).join(&schedule.to);
+
+    if map_exists(&to_abs).await {
+        let manifest = RenderManifest::load(&to_abs)
+            .await
+            .ok_or("target is a map that was not rendered here")?;
+        let mut task =
+            RenderTask::update_of(schedule.user, schedule.to.clone(), manifest, keep_versions);
+        task.from = schedule.from.clone();
+        task.preset = schedule.preset.clone();
+        return Ok(task);
+    }
+
+    if fs::try_exists(&to_abs).await.unwrap_or(true) {
+        return Err("target path occupied".to_string());
+    }
+
+    Ok(RenderTask {
+        from: schedule.from.clone(),
+        to: schedule.to.clone(),
+        preset: schedule.preset.clone(),
+        user: schedule.user,
+        job: 0,
+        update: false,
+        dimensions: Vec::new(),
+        area: Default::default(),
+        overrides: Default::default(),
+        keep_versions: 0,
+    })
+}
+
+async fn run_schedule(jobs: &Data<Jobs>, schedule: RenderSchedule) -> Result<(), Box<dyn Error>> {
+    let renders = RENDER_JOBS.get().unwrap();
+    let started = now();
+    let next_run = schedule
+        .cron()
+        .ok()
+        .and_then(|cron| cron.next_after(started));
+
+    // a run still going when the next is due is not queued twice
+    if schedule.last_run.as_ref().is_some_and(|run| {
+        run.finished.is_none()
+            && renders
+                .status(run.job, schedule.user)
+                .is_some_and(|(status, _)| !status.is_finished())
+    }) {
+        RenderSchedule::set_runs(schedule.id, next_run, None).await?;
+        return Ok(());
+    }
+
+    // the account is gone, left alone the schedule would stay due and be retried every check
+    let Some(account) = account_by_id(schedule.user).await? else {
+        RenderSchedule::delete(schedule.user, schedule.id).await?;
+        return Ok(());
+    };
+
+    let checked = match schedule_task(
+        &schedule,
+        VALUES.get().config.snapshot_limit(&account.limit),
+    )
+    .await
+    {
+        Ok(task) => check_unattended(&account, &task).await.map(|()| task),
+        Err(reason) => Err(reason),
+    };
+    let task = match checked {
+        Ok(task) => renders.register(task),
+        Err(reason) => {
+            let run = ScheduleRun {
+                job: 0,
+                started,
+                finished: Some(started),
+                status: RenderStatus::Failed {
+                    error: serde_json::Value::String(reason),
+                },
+            };
+            RenderSchedule::set_runs(schedule.id, next_run, Some(&run)).await?;
+            return Ok(());
+        }
+    };
+    renders.log(
+        task.job,
+        format!("Queued by schedule {}", schedule.schedule),
+    );
+
+    let job = task.job;
+    let run = ScheduleRun {
+        job,
+        started,
+        finished: None,
+        status: RenderStatus::Queued,
+    };
+    RenderSchedule::set_runs(schedule.id, next_run, Some(&run)).await?;
+
+    let (jobs, id, user) = (jobs.clone(), schedule.id, schedule.user);
+    actix_web::rt::spawn(async move {
+        let renders = RENDER_JOBS.get().unwrap();
+        let _ = renders.run(&jobs, &account, task).await;
+
+        let status = renders
+            .status(job, user)
+            .map(|(status, _)| status)
+            .unwrap_or(RenderStatus::Cancelled);
+        let run = ScheduleRun {
+            job,
+            started,
+            finished: Some(now()),
+            status,
+        };
+        if let Err(e) = RenderSchedule::set_last_run(id, &run).await {
+            log::warn!("failed to record run of schedule {id}: {e}");
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use goodmorning_services::{
+    functions::{get_user_dir, get_usersys_dir},
+    structs::GMServices,
+};
+use tokio::fs;
+
+use crate::structs::{RenderManifest, Snapshot, V1BlueError};
+
+use super::{dir_size, now};
+
+/// Seconds the snapshots of a map that seems gone are kept for, its manifest may only be missing
+/// for a while or have been put back by hand.
+const ORPHAN_GRACE: u64 = 7 * 24 * 60 * 60;
+/// Marks a snapshots folder whose map was not found, holding when that was first seen.
+const ORPHAN_MARKER: &str = ".orphaned";
+
+/// Every kept version of `user`'s maps, a folder per map id.
+///
+/// Kept out of the owner's directory so they are not listed or rendered into as maps.
+fn snapshots_root(user: i64) -> PathBuf {
+    get_usersys_dir(user, Some(GMServices::Blue)).join("snapshots")
+}
+
+/// Where the previous versions of the map with id `map_id` are kept.
+pub fn snapshots_dir(user: i64, map_id: u64) -> PathBuf {
+    snapshots_root(user).join(map_id.to_string())
+}
+
+/// Id tying a map to its snapshots, `map` relative to the owner's directory.
+///
+/// Read from the map's manifest, so the snapshots follow the map when it is moved.
+pub async fn map_id(user: i64, map: &Path) -> Option<u64> {
+    RenderManifest::load(&get_user_dir(user, None).join(map))
+        .await?
+        .map_id
+}
+
+/// A previous version of a map, `None` if the map has never kept one.
+pub async fn snapshot_path(user: i64, map: &Path, id: u64) -> Option<PathBuf> {
+    Some(snapshots_dir(user, map_id(user, map).await?).join(id.to_string()))
+}
+
+/// Moves a map into the snapshots of `map_id`, named after when it finished rendering.
+pub async fn take_snapshot(user: i64, map: &Path, map_id: u64) -> io::Result<u64> {
+    let map_abs = get_user_dir(user, None).join(map);
+    let mut id = RenderManifest::load(&map_abs)
+        .await
+        .map(|manifest| manifest.finished)
+        .unwrap_or_else(now);
+
+    let dir = snapshots_dir(user, map_id);
+    fs::create_dir_all(&dir).await?;
+    while fs::try_exists(dir.join(id.to_string())).await? {
+        id += 1;
+    }
+
+    fs::rename(&map_abs, dir.join(id.to_string())).await?;
+    Ok(id)
+}
+
+/// Previous versions of a map, newest first.
+pub async fn list_snapshots(user: i64, map: &Path) -> Vec<Snapshot> {
+    match map_id(user, map).await {
+        Some(map_id) => map_snapshots(user, map_id).await,
+        None => Vec::new(),
+    }
+}
+
+/// Previous versions of the map with id `map_id`, newest first.
+async fn map_snapshots(user: i64, map_id: u64) -> Vec<Snapshot> {
+    let mut snapshots = Vec::new();
+    let Ok(mut entries) = fs::read_dir(snapshots_dir(user, map_id)).await else {
+        return snapshots;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+        else {
+            continue;
+        };
+
+        if !entry.file_type().await.is_ok_and(|kind| kind.is_dir()) {
+            continue;
+        }
+
+        snapshots.push(Snapshot {
+            id,
+            manifest: RenderManifest::load(&entry.path()).await,
+        });
+    }
+
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.id));
+    snapshots
+}
+
+/// Removes the oldest snapshots of the map with id `map_id` until at most `keep` are left.
+pub async fn prune_snapshots(user: i64, map_id: u64, keep: usize) -> io::Result<()> {
+    for snapshot in map_snapshots(user, map_id).await.into_iter().skip(keep) {
+        fs::remove_dir_all(snapshots_dir(user, map_id).join(snapshot.id.to_string())).await?;
+    }
+    Ok(())
+}
+
+pub async fn delete_snapshot(
+    user: i64,
+    map: &Path,
+    id: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = snapshot_path(user, map, id).await else {
+        return Err(V1BlueError::SnapshotNotFound.into());
+    };
+    if !fs::try_exists(&path).await? {
+        return Err(V1BlueError::SnapshotNotFound.into());
+    }
+
+    fs::remove_dir_all(path).await?;
+    Ok(())
+}
+
+/// Bytes `user`'s snapshots take.
+pub async fn snapshots_size(user: i64) -> u64 {
+    dir_size(&snapshots_root(user)).await
+}
+
+/// Removes `user`'s snapshots of maps that have been gone for `ORPHAN_GRACE`.
+///
+/// Maps are deleted and moved through the storage API without GM Blue seeing it, so a map id no
+/// map or staged render has any more means its map is gone. A moved map keeps its id and with it
+/// its snapshots. Snapshots are only marked the first time their map is not found, and unmarked if
+/// it shows up again.
+pub async fn clean_snapshots(user: i64) {
+    let Ok(mut entries) = fs::read_dir(snapshots_root(user)).await else {
+        return;
+    };
+
+    let mut live = map_ids(&get_user_dir(user, None)).await;
+    live.extend(map_ids(&get_usersys_dir(user, Some(GMServices::Blue)).join("staging")).await);
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Some(map_id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+        else {
+            continue;
+        };
+
+        let marker = entry.path().join(ORPHAN_MARKER);
+        if live.contains(&map_id) {
+            let _ = fs::remove_file(&marker).await;
+            continue;
+        }
+
+        let orphaned = fs::read_to_string(&marker)
+            .await
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok());
+        let res = match orphaned {
+            Some(orphaned) if now() >= orphaned + ORPHAN_GRACE => {
+                log::info!(
+                    "removing snapshots of map {map_id} of user {user}, gone since {orphaned}"
+                );
+                fs::remove_dir_all(entry.path()).await
+            }
+            Some(_) => Ok(()),
+            None => fs::write(&marker, now().to_string()).await,
+        };
+        if let Err(e) = res {
+            log::warn!("failed to clean up snapshots of missing map {map_id}: {e}");
+        }
+    }
+}
+
+/// Ids of the maps under `dir`, not looking inside maps or the `.system` folder.
+async fn map_ids(dir: &Path) -> HashSet<u64> {
+    let mut ids = HashSet::new();
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        if let Some(manifest) = RenderManifest::load(&dir).await {
+            ids.extend(manifest.map_id);
+            continue;
+        }
+
+        let Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_name() != ".system"
+                && entry.file_type().await.is_ok_and(|kind| kind.is_dir())
+            {
+                stack.push(entry.path());
+            }
+        }
+    }
+
+    ids
+}
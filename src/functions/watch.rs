@@ -0,0 +1,130 @@
+use std::{error::Error, path::Path, time::Duration};
+
+use actix_web::web::Data;
+use goodmorning_services::{functions::get_user_dir, structs::Jobs};
+use tokio::fs;
+
+use crate::{
+    structs::{MapWatch, RenderManifest, RenderTask},
+    values::{RENDER_JOBS, VALUES},
+};
+
+use super::{
+    account_by_id, check_unattended, last_region_change, now, preset_dimension, preset_path,
+    region_dir, ArchiveKind,
+};
+
+/// Checks watched maps every `watch_interval` seconds, queueing an update for each map whose world
+/// changed since it was rendered and has since settled for `watch_debounce` seconds.
+pub async fn watch_maps(jobs: Data<Jobs>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(VALUES.get().config.watch_interval)).await;
+
+        let watches = match MapWatch::all().await {
+            Ok(watches) => watches,
+            Err(e) => {
+                log::error!("failed to load watched maps: {e}");
+                continue;
+            }
+        };
+
+        for watch in watches.into_iter().filter(|watch| !watch.paused) {
+            if let Err(e) = check_watch(&jobs, watch.clone()).await {
+                log::warn!(
+                    "failed to check watched map {} of {}: {e}",
+                    watch.map.to_string_lossy(),
+                    watch.user
+                );
+            }
+        }
+    }
+}
+
+/// When the world a map was rendered from last changed, `None` if it is gone.
+async fn last_change(world: &Path, manifest: &RenderManifest, user: i64) -> Option<u64> {
+    if ArchiveKind::from_path(world).is_some() {
+        return fs::metadata(world)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()
+            .map(|modified| modified.as_secs());
+    }
+
+    let dimensions = if manifest.dimensions.is_empty() {
+        vec![preset_dimension(&preset_path(user, &manifest.preset)).await]
+    } else {
+        manifest.dimensions.clone()
+    };
+
+    let mut last = None;
+    for dimension in dimensions.iter() {
+        last = last.max(last_region_change(&region_dir(world, dimension), &manifest.area).await);
+    }
+    last
+}
+
+async fn check_watch(jobs: &Data<Jobs>, mut watch: MapWatch) -> Result<(), Box<dyn Error>> {
+    let renders = RENDER_JOBS.get().unwrap();
+    if watch.last_job.is_some_and(|id| {
+        renders
+            .status(id, watch.user)
+            .is_some_and(|(status, _)| !status.is_finished())
+    }) {
+        return Ok(());
+    }
+
+    let map_abs = get_user_dir(watch.user, None).join(&watch.map);
+    let Some(manifest) = RenderManifest::load(&map_abs).await else {
+        return Ok(());
+    };
+
+    let world = get_user_dir(watch.user, None).join(&manifest.from);
+    let Some(changed) = last_change(&world, &manifest, watch.user).await else {
+        return Ok(());
+    };
+
+    // a failed update is only retried once the world changes again
+    if changed < manifest.started
+        || watch.last_queued.is_some_and(|queued| changed < queued)
+        || now().saturating_sub(changed) < VALUES.get().config.watch_debounce
+    {
+        return Ok(());
+    }
+
+    let Some(account) = account_by_id(watch.user).await? else {
+        return Ok(());
+    };
+
+    let task = RenderTask::update_of(
+        watch.user,
+        watch.map.clone(),
+        manifest,
+        VALUES.get().config.snapshot_limit(&account.limit),
+    );
+    watch.last_queued = Some(now());
+
+    if let Err(reason) = check_unattended(&account, &task).await {
+        log::info!(
+            "not updating watched map {} of {}: {reason}",
+            watch.map.to_string_lossy(),
+            watch.user
+        );
+        // skipped like a failed update, until the world changes again
+        watch.set_queued().await?;
+        return Ok(());
+    }
+
+    let task = renders.register(task);
+    renders.log(task.job, "Queued by auto update, the world changed");
+    watch.last_job = Some(task.job);
+    watch.set_queued().await?;
+
+    let jobs = jobs.clone();
+    actix_web::rt::spawn(async move {
+        let _ = RENDER_JOBS.get().unwrap().run(&jobs, &account, task).await;
+    });
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use bluemap_singleserve::Map;
+use serde_json::Value;
+use tokio::fs;
+
+use crate::structs::{V1BlueError, IMPORT_FILE};
+
+/// Whether `path` holds a map, rendered here or imported.
+pub async fn map_exists(path: &Path) -> bool {
+    Map::exists(path).await
+        || fs::try_exists(path.join(IMPORT_FILE))
+            .await
+            .unwrap_or(false)
+}
+
+/// Checks a folder is a BlueMap webapp output: an `index.html`, and a `settings.json` listing
+/// maps that are all there. Returns the maps listed.
+pub async fn validate_webapp(root: &Path) -> Result<Vec<String>, V1BlueError> {
+    let invalid = |reason: String| V1BlueError::InvalidImport { reason };
+
+    if !fs::try_exists(root.join("index.html"))
+        .await
+        .unwrap_or(false)
+    {
+        return Err(invalid("no index.html".to_string()));
+    }
+
+    let settings = read_settings(root)
+        .await
+        .map_err(|e| invalid(format!("settings.json could not be read: {e}")))?;
+    let maps = settings
+        .get("maps")
+        .and_then(Value::as_array)
+        .filter(|maps| !maps.is_empty())
+        .ok_or_else(|| invalid("settings.json lists no maps".to_string()))?;
+
+    let mut names = Vec::with_capacity(maps.len());
+    for map in maps {
+        let Some(map) = map.as_str() else {
+            return Err(invalid(
+                "settings.json has a map that is not a name".to_string(),
+            ));
+        };
+
+        if map.is_empty() || map.contains(['/', '\\']) || map == ".." {
+            return Err(invalid(format!("map {map} has an invalid name")));
+        }
+
+        if !fs::metadata(root.join("maps").join(map))
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(invalid(format!("map {map} is missing from maps/")));
+        }
+        names.push(map.to_string());
+    }
+
+    Ok(names)
+}
+
+/// `settings.json` keys an imported webapp keeps, none of them point the webapp at other files.
+const IMPORT_SETTINGS: &[&str] = &[
+    "version",
+    "useCookies",
+    "enableFreeFlight",
+    "defaultToFlatView",
+    "resolutionDefault",
+    "minZoomDistance",
+    "maxZoomDistance",
+    "hiresSliderMax",
+    "hiresSliderDefault",
+    "hiresSliderMin",
+    "lowresSliderMax",
+    "lowresSliderDefault",
+    "lowresSliderMin",
+];
+
+/// Whether `file`, relative to an imported webapp's root, is map data to keep.
+///
+/// Only tiles, textures and map settings of the `maps` listed are kept. Markers are left out,
+/// BlueMap shows their labels as HTML, and so is anything else that could run in the browser.
+pub fn import_file(file: &Path, maps: &[String]) -> bool {
+    let parts = file
+        .iter()
+        .map(|part| part.to_str().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    match parts.as_slice() {
+        ["maps", map, rest @ ..] if maps.iter().any(|name| name == map) => match rest {
+            ["settings.json" | "textures.json" | "textures.json.gz"] => true,
+            ["tiles", .., name] => [".png", ".prbm", ".json", ".gz"]
+                .iter()
+                .any(|extension| name.ends_with(extension)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// The `settings.json` of an imported webapp rewritten for GM Blue to serve: only the display
+/// options in `IMPORT_SETTINGS` are kept, without custom scripts or styles and with map data read
+/// from the webapp's own `maps` folder.
+pub async fn import_settings(root: &Path, maps: &[String]) -> io::Result<Value> {
+    let imported = read_settings(root).await?;
+    let mut settings = serde_json::Map::new();
+
+    for key in IMPORT_SETTINGS {
+        if let Some(value) = imported
+            .get(key)
+            .filter(|value| value.is_number() || value.is_boolean() || value.is_string())
+        {
+            settings.insert(key.to_string(), value.clone());
+        }
+    }
+
+    settings.insert("maps".to_string(), Value::from(maps.to_vec()));
+    settings.insert("mapDataRoot".to_string(), Value::from("maps"));
+    settings.insert("liveDataRoot".to_string(), Value::from("maps"));
+    settings.insert("scripts".to_string(), Value::Array(Vec::new()));
+    settings.insert("styles".to_string(), Value::Array(Vec::new()));
+    Ok(Value::Object(settings))
+}
+
+/// Root of the BlueMap webapp in a map output, where `settings.json` and the `maps` folder are.
+pub async fn webroot(map: &Path) -> PathBuf {
+    let web = map.join("web");
+    if fs::try_exists(web.join("settings.json"))
+        .await
+        .unwrap_or(false)
+    {
+        web
+    } else {
+        map.to_path_buf()
+    }
+}
+
+async fn read_settings(root: &Path) -> io::Result<Value> {
+    serde_json::from_slice(&fs::read(root.join("settings.json")).await?).map_err(io::Error::other)
+}
+
+pub async fn write_settings(root: &Path, settings: &Value) -> io::Result<()> {
+    fs::write(
+        root.join("settings.json"),
+        serde_json::to_vec_pretty(settings)?,
+    )
+    .await
+}
+
+/// Moves the map BlueMap rendered into `from` over to `into` as map `id`, and lists it in the
+/// webapp's `settings.json` so the map switcher picks it up.
+///
+/// If `into` does not exist yet the whole output of `from` becomes `into`, webapp included.
+pub async fn merge_map(from: &Path, into: &Path, id: &str) -> io::Result<()> {
+    let from_root = webroot(from).await;
+    let rendered = read_settings(&from_root)
+        .await?
+        .get("maps")
+        .and_then(Value::as_array)
+        .and_then(|maps| maps.first())
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| io::Error::other("no map in render output"))?;
+
+    if !fs::try_exists(into).await? {
+        fs::rename(from, into).await?;
+        let root = webroot(into).await;
+        if rendered != id {
+            fs::rename(
+                root.join("maps").join(&rendered),
+                root.join("maps").join(id),
+            )
+            .await?;
+        }
+
+        let mut settings = read_settings(&root).await?;
+        settings["maps"] = Value::from(vec![id]);
+        return write_settings(&root, &settings).await;
+    }
+
+    let root = webroot(into).await;
+    fs::rename(
+        from_root.join("maps").join(&rendered),
+        root.join("maps").join(id),
+    )
+    .await?;
+
+    let mut settings = read_settings(&root).await?;
+    match settings.get_mut("maps").and_then(Value::as_array_mut) {
+        Some(maps) => {
+            if !maps.iter().any(|map| map.as_str() == Some(id)) {
+                maps.push(Value::from(id))
+            }
+        }
+        None => settings["maps"] = Value::from(vec![id]),
+    }
+    write_settings(&root, &settings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_file_keeps_map_data_only() {
+        let maps = vec!["world".to_string()];
+        let kept = |file: &str| import_file(Path::new(file), &maps);
+
+        assert!(kept("maps/world/settings.json"));
+        assert!(kept("maps/world/textures.json.gz"));
+        assert!(kept("maps/world/tiles/0/x1/z2.prbm.gz"));
+        assert!(kept("maps/world/tiles/1/x1/z2.png"));
+
+        assert!(!kept("index.html"));
+        assert!(!kept("settings.json"));
+        assert!(!kept("assets/index.js"));
+        assert!(!kept("maps/world/live/markers.json"));
+        assert!(!kept("maps/world/tiles/1/x1/z2.html"));
+        assert!(!kept("maps/world/index.html"));
+        assert!(!kept("maps/other/settings.json"));
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use tokio::{fs, io::AsyncReadExt};
+
+use crate::structs::{LevelInfo, RenderArea, V1BlueError, WorldDimension, WorldInfo};
+
+pub const OVERWORLD: &str = "minecraft:overworld";
+pub const NETHER: &str = "minecraft:the_nether";
+pub const END: &str = "minecraft:the_end";
+
+/// Resolves a dimension as a user would name it: `overworld`, `nether`, `end`, the world folder
+/// names `DIM-1` and `DIM1`, or a full id such as `minecraft:the_nether`.
+pub fn parse_dimension(dimension: &str) -> Result<String, V1BlueError> {
+    let parsed = match dimension.trim().to_lowercase().as_str() {
+        "overworld" | "world" => OVERWORLD.to_string(),
+        "nether" | "the_nether" | "dim-1" => NETHER.to_string(),
+        "end" | "the_end" | "dim1" => END.to_string(),
+        other if other.contains(':') => other.to_string(),
+        other => format!("minecraft:{other}"),
+    };
+
+    if !valid_dimension(&parsed) {
+        return Err(V1BlueError::InvalidDimension {
+            dimension: dimension.to_string(),
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Whether `dimension` is a `namespace:path` id, as Minecraft names them.
+///
+/// Dimension ids end up as folders under the world and as strings in presets, so anything else,
+/// including `..` and empty path segments, is rejected rather than cleaned up.
+pub fn valid_dimension(dimension: &str) -> bool {
+    let Some((namespace, path)) = dimension.split_once(':') else {
+        return false;
+    };
+
+    namespace
+        .chars()
+        .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '.' | '-'))
+        && path
+            .chars()
+            .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '.' | '-' | '/'))
+        && [namespace]
+            .into_iter()
+            .chain(path.split('/'))
+            .all(|segment| !matches!(segment, "" | "." | ".."))
+}
+
+/// Map id of a dimension in a multi-dimension output, e.g. `the_nether`.
+pub fn dimension_map_id(dimension: &str) -> String {
+    let (_, path) = dimension.split_once(':').unwrap_or(("", dimension));
+    path.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Name shown in the map switcher for a dimension.
+pub fn dimension_name(dimension: &str) -> String {
+    match dimension {
+        OVERWORLD => "Overworld".to_string(),
+        NETHER => "Nether".to_string(),
+        END => "End".to_string(),
+        other => dimension_map_id(other),
+    }
+}
+
+/// Oldest data version BlueMap renders, Minecraft 1.13.
+pub const MIN_DATA_VERSION: i64 = 1519;
+
+/// Checks a render source is a world BlueMap can render, before it takes up a job slot.
+///
+/// `level.dat` has to parse, be recent enough, and every dimension has to have a region folder.
+pub async fn check_world(world: &Path, dimensions: &[String]) -> Result<LevelInfo, V1BlueError> {
+    if !fs::try_exists(world.join("level.dat"))
+        .await
+        .unwrap_or(false)
+    {
+        return Err(V1BlueError::NotAWorld);
+    }
+
+    let level = LevelInfo::load(world)
+        .await
+        .map_err(|reason| V1BlueError::InvalidLevelDat { reason })?;
+
+    if let Some(data_version) = level.data_version {
+        if data_version < MIN_DATA_VERSION {
+            return Err(V1BlueError::UnsupportedWorldVersion {
+                data_version,
+                version: level.version.clone(),
+            });
+        }
+    }
+
+    for dimension in dimensions {
+        if !fs::try_exists(region_dir(world, dimension))
+            .await
+            .unwrap_or(false)
+        {
+            return Err(V1BlueError::DimensionNotFound {
+                dimension: dimension.clone(),
+            });
+        }
+    }
+
+    Ok(level)
+}
+
+/// Dimensions with a region folder in a world, the vanilla ones first.
+pub async fn world_dimensions(world: &Path) -> Vec<String> {
+    let mut dimensions = Vec::new();
+    for dimension in [OVERWORLD, NETHER, END] {
+        if fs::try_exists(region_dir(world, dimension))
+            .await
+            .unwrap_or(false)
+        {
+            dimensions.push(dimension.to_string());
+        }
+    }
+
+    let mut custom = Vec::new();
+    if let Ok(mut namespaces) = fs::read_dir(world.join("dimensions")).await {
+        while let Ok(Some(namespace)) = namespaces.next_entry().await {
+            let Ok(mut paths) = fs::read_dir(namespace.path()).await else {
+                continue;
+            };
+
+            while let Ok(Some(path)) = paths.next_entry().await {
+                let dimension = format!(
+                    "{}:{}",
+                    namespace.file_name().to_string_lossy(),
+                    path.file_name().to_string_lossy()
+                );
+                if !dimensions.contains(&dimension)
+                    && fs::try_exists(path.path().join("region"))
+                        .await
+                        .unwrap_or(false)
+                {
+                    custom.push(dimension);
+                }
+            }
+        }
+    }
+    custom.sort();
+    dimensions.extend(custom);
+
+    dimensions
+}
+
+/// Reads `level.dat` and counts the regions of every dimension, the seed is left out unless the
+/// world is the viewer's own.
+pub async fn world_info(world: &Path, owner: bool) -> Result<WorldInfo, V1BlueError> {
+    if !fs::try_exists(world.join("level.dat"))
+        .await
+        .unwrap_or(false)
+    {
+        return Err(V1BlueError::NotAWorld);
+    }
+
+    let mut level = LevelInfo::load(world)
+        .await
+        .map_err(|reason| V1BlueError::InvalidLevelDat { reason })?;
+    if !owner {
+        level.seed = None;
+    }
+
+    let mut dimensions = Vec::new();
+    for dimension in world_dimensions(world).await {
+        dimensions.push(WorldDimension {
+            regions: count_regions(&region_dir(world, &dimension), &RenderArea::default()).await,
+            dimension,
+        });
+    }
+
+    Ok(WorldInfo { level, dimensions })
+}
+
+/// Region folder of a dimension inside a world save.
+pub fn region_dir(world: &Path, dimension: &str) -> PathBuf {
+    match dimension {
+        OVERWORLD => world.join("region"),
+        NETHER => world.join("DIM-1").join("region"),
+        END => world.join("DIM1").join("region"),
+        other => {
+            let (namespace, path) = other.split_once(':').unwrap_or(("minecraft", other));
+            world
+                .join("dimensions")
+                .join(namespace)
+                .join(path)
+                .join("region")
+        }
+    }
+}
+
+/// Coordinates of a region file, from its `r.<x>.<z>.mca` name.
+pub fn region_coords(file: &Path) -> Option<(i32, i32)> {
+    if file.extension() != Some(OsStr::new("mca")) {
+        return None;
+    }
+
+    let name = file.file_stem()?.to_str()?;
+    let mut parts = name.strip_prefix("r.")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    Some((x, z))
+}
+
+fn in_area(file: &Path, area: &RenderArea) -> bool {
+    region_coords(file).is_some_and(|(x, z)| area.contains_region(x, z))
+}
+
+/// Number of region files inside `area` in a region folder, 0 if there is no such folder.
+pub async fn count_regions(dir: &Path, area: &RenderArea) -> u64 {
+    let mut count = 0;
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return 0;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if in_area(&entry.path(), area) {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+/// Number of region files inside `area` in a region folder modified after `since`, in seconds
+/// since epoch.
+pub async fn count_changed_regions(dir: &Path, since: u64, area: &RenderArea) -> u64 {
+    let mut count = 0;
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return 0;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if !in_area(&entry.path(), area) {
+            continue;
+        }
+
+        let modified = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+        if modified.is_none_or(|modified| modified.as_secs() >= since) {
+            count += 1;
+        }
+    }
+
+    count
+}
+
+/// When a region file inside `area` was last modified, in seconds since epoch.
+pub async fn last_region_change(dir: &Path, area: &RenderArea) -> Option<u64> {
+    let mut last = None;
+    let mut entries = fs::read_dir(dir).await.ok()?;
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if !in_area(&entry.path(), area) {
+            continue;
+        }
+
+        let modified = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs());
+        last = last.max(modified);
+    }
+
+    last
+}
+
+/// Number of chunks saved in the region files inside `area`, from the offset table at the start of
+/// each file. With `since`, only files modified after it are counted.
+pub async fn count_chunks(dir: &Path, area: &RenderArea, since: Option<u64>) -> u64 {
+    let mut count = 0;
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return 0;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if !in_area(&entry.path(), area) {
+            continue;
+        }
+
+        if let Some(since) = since {
+            let modified = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+            if modified.is_some_and(|modified| modified.as_secs() < since) {
+                continue;
+            }
+        }
+
+        // 1024 big endian entries, one per chunk, 0 where the chunk was never generated
+        let mut offsets = [0; 4096];
+        let Ok(mut file) = fs::File::open(entry.path()).await else {
+            continue;
+        };
+        if file.read_exact(&mut offsets).await.is_err() {
+            continue;
+        }
+
+        count += offsets
+            .chunks_exact(4)
+            .filter(|offset| offset.iter().any(|b| *b != 0))
+            .count() as u64;
+    }
+
+    count
+}
+
+/// Number of hires tiles BlueMap has written so far under a map output.
+pub async fn count_tiles(map: &Path) -> u64 {
+    let mut count = 0;
+    let mut stack = vec![(map.to_path_buf(), false)];
+
+    while let Some((dir, in_tiles)) = stack.pop() {
+        let Ok(mut entries) = fs::read_dir(&dir).await else {
+            continue;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(file_type) = entry.file_type().await else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                // hires tiles are stored at lod 0, under tiles/0/
+                let is_hires = dir.file_name() == Some(OsStr::new("tiles"))
+                    && entry.file_name() == OsStr::new("0");
+                stack.push((entry.path(), in_tiles || is_hires));
+            } else if in_tiles {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
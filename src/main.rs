@@ -2,7 +2,7 @@ use actix_web::{web::Data, App, HttpServer};
 use gm_blue::{
     pages,
     r#static::{r#static, remindverify, static_services},
-    values::BLUE_CONFIG,
+    values::VALUES,
 };
 use goodmorning_services::structs::Jobs;
 
@@ -12,6 +12,14 @@ async fn main() {
     gm_blue::values::init();
 
     let jobs: Data<Jobs> = Data::new(Jobs::default());
+    if let Err(e) = gm_blue::functions::resume_jobs(jobs.clone()).await {
+        log::error!("failed to resume stored jobs: {e}");
+    }
+
+    #[cfg(unix)]
+    actix_web::rt::spawn(reload_on_hangup());
+    actix_web::rt::spawn(gm_blue::functions::watch_maps(jobs.clone()));
+    actix_web::rt::spawn(gm_blue::functions::run_schedules(jobs.clone()));
 
     HttpServer::new(move || {
         App::new()
@@ -21,13 +29,35 @@ async fn main() {
             .service(gm_blue::api::scope())
             .service(pages::home)
             .service(pages::render)
+            .service(pages::snapshot)
+            .service(pages::compare)
             .service(pages::fspath)
             .service(pages::root)
             .app_data(jobs.clone())
     })
-    .bind(("0.0.0.0", BLUE_CONFIG.get().unwrap().port))
+    .bind(("0.0.0.0", VALUES.get().config.port))
     .unwrap()
     .run()
     .await
     .unwrap();
 }
+
+/// Reloads the blue config and presets every time the process receives SIGHUP.
+#[cfg(unix)]
+async fn reload_on_hangup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            log::error!("failed to listen for SIGHUP: {e}");
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        if let Err(e) = gm_blue::values::reload().await {
+            log::error!("reload failed, keeping the current config: {e}");
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{error::Error, fmt::Write, path::PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::{
+    get,
+    http::header::ContentType,
+    web::{self, Path},
+    HttpRequest, HttpResponse,
+};
+use goodmorning_services::{
+    bindings::services::v1::V1Error,
+    functions::{cookie_to_str, get_user_dir, has_dotdot},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::topbar_from_req,
+    functions::{format_time, from_res, list_snapshots, map_exists},
+    structs::RenderManifest,
+    values::VALUES,
+};
+
+#[derive(Serialize, Deserialize)]
+struct Query {
+    /// Snapshot shown on the left, the current version if not given.
+    a: Option<u64>,
+    /// Snapshot shown on the right, the newest snapshot if not given.
+    b: Option<u64>,
+    #[serde(default)]
+    slider: bool,
+}
+
+/// Two versions of a map next to each other, or on top of each other with a swipe slider.
+#[get("/compare/{path:.*}")]
+pub async fn compare(
+    path: Path<String>,
+    req: HttpRequest,
+    query: web::Query<Query>,
+) -> HttpResponse {
+    from_res(compare_task(path, &req, query).await, &req).await
+}
+
+async fn compare_task(
+    path: Path<String>,
+    req: &HttpRequest,
+    query: web::Query<Query>,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    let token_cookie = req.cookie("token");
+    let token = cookie_to_str(&token_cookie);
+
+    if token.is_none() {
+        return Ok(NamedFile::open_async(
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
+        )
+        .await
+        .map(|file| file.into_response(req))?);
+    }
+
+    let (topbar, account) = match topbar_from_req(req).await? {
+        Ok(stuff) => stuff,
+        Err(res) => return Ok(res),
+    };
+
+    let account = if let Some(account) = account {
+        account.v1_restrict_verified()?
+    } else {
+        return Ok(NamedFile::open_async(
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
+        )
+        .await?
+        .into_response(req));
+    };
+
+    let map = path.trim_matches('/').to_string();
+    let map_path = PathBuf::from("blue").join(&map);
+    let map_abs = get_user_dir(account.id, None).join(&map_path);
+    if has_dotdot(&map_path) || !map_exists(&map_abs).await {
+        return Err(V1Error::FileNotFound.into());
+    }
+
+    let snapshots = list_snapshots(account.id, &map_path).await;
+    let exists = |id: Option<u64>| id.is_none_or(|id| snapshots.iter().any(|s| s.id == id));
+    let b = query.b.or(snapshots.first().map(|snapshot| snapshot.id));
+    if !exists(query.a) || !exists(b) {
+        return Err(V1Error::FileNotFound.into());
+    }
+
+    let current = RenderManifest::load(&map_abs)
+        .await
+        .map(|manifest| format!("Current version ({})", format_time(manifest.finished)))
+        .unwrap_or_else(|| "Current version".to_string());
+
+    let versions = |selected: Option<u64>| {
+        snapshots.iter().fold(
+            format!(
+                r#"<option value="" {}>{}</option>"#,
+                if selected.is_none() { "selected" } else { "" },
+                html_escape::encode_text(&current)
+            ),
+            |mut buf, version| {
+                write!(
+                    buf,
+                    r#"<option value="{}" {}>{}</option>"#,
+                    version.id,
+                    if selected == Some(version.id) {
+                        "selected"
+                    } else {
+                        ""
+                    },
+                    version.date()
+                )
+                .unwrap();
+                buf
+            },
+        )
+    };
+    let (versions_a, versions_b) = (versions(query.a), versions(b));
+
+    let map_escaped = html_escape::encode_double_quoted_attribute(&map);
+    let (side_selected, slider_selected) = if query.slider {
+        ("", "selected")
+    } else {
+        ("selected", "")
+    };
+
+    let html = format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <link rel="stylesheet" href="/static/css/main.css" />
+    <link rel="stylesheet" href="/static/css/topbar.css" />
+    <link rel="stylesheet" href="/static/css/compare.css" />
+    <link rel="stylesheet" href="/static/css/topbar-loggedin.css" />
+    <link rel="stylesheet" href="/static/css/dark/main.css" />
+    <link rel="stylesheet" href="/static/css/dark/topbar.css" />
+    <link
+      rel="shortcut icon"
+      href="/static/images/logo.webp"
+      type="image/x-icon"
+    />
+    <title>Compare {map_escaped} - GM Blue</title>
+  </head>
+  <body>
+    {topbar}
+<div id="compare-controls" map="{map_escaped}">
+    <a href="/fs/{map_escaped}" id="back">Back to map</a>
+    <select id="version-a">{versions_a}</select>
+    <select id="mode">
+        <option value="side" {side_selected}>Side by side</option>
+        <option value="slider" {slider_selected}>Slider</option>
+    </select>
+    <select id="version-b">{versions_b}</select>
+    <label><input type="checkbox" id="sync" checked /> Sync cameras</label>
+</div>
+<div id="compare">
+    <iframe id="viewer-a"></iframe>
+    <iframe id="viewer-b"></iframe>
+    <input type="range" id="slider" class="hide" min="0" max="100" value="50" />
+</div>
+    <script src="/static/scripts/compare.js" defer></script>
+    <script src="/static/scripts/topbar.js" defer></script>
+  </body>
+</html>"#,
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
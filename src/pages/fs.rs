@@ -1,4 +1,4 @@
-use std::{borrow::Cow, error::Error, path::PathBuf};
+use std::{borrow::Cow, error::Error, fmt::Write, path::PathBuf};
 
 use actix_files::NamedFile;
 use actix_web::http::header::HeaderValue;
@@ -6,7 +6,7 @@ use actix_web::routes;
 use actix_web::{get, http::header::ContentType, web::Path, HttpRequest, HttpResponse};
 use bluemap_singleserve::Map;
 use goodmorning_services::bindings::services::v1::{AccessType, V1Error};
-use goodmorning_services::functions::{cookie_to_str, get_usersys_dir};
+use goodmorning_services::functions::{cookie_to_str, get_usersys_dir, has_dotdot};
 use goodmorning_services::traits::CollectionItem;
 use goodmorning_services::ACCOUNTS;
 use goodmorning_services::{
@@ -17,8 +17,9 @@ use tokio::fs;
 
 use crate::{
     components::{self, topbar_from_req, FsItem, FsItemProp, PathProp},
-    functions::{from_res, gen_nonce},
-    values::BLUE_CONFIG,
+    functions::{from_res, gen_nonce, list_snapshots, map_exists, snapshot_path, world_info},
+    structs::{ImportManifest, MapWatch, RenderManifest, Snapshot, V1BlueError, WatchState},
+    values::VALUES,
 };
 
 #[get("/fs/{path:.*}")]
@@ -41,7 +42,7 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
 
     if token.is_none() {
         return Ok(NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/login.html"),
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
         )
         .await
         .map(|file| file.into_response(req))?);
@@ -56,7 +57,7 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
         account.v1_restrict_verified()?
     } else {
         return Ok(NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/login.html"),
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
         )
         .await?
         .into_response(req));
@@ -124,7 +125,7 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
                     .chain(pathbuf.iter().skip(3))
                     .collect::<PathBuf>();
                 for parent in pathbuf.ancestors() {
-                    if Map::exists(&base_abs.join(parent)).await {
+                    if map_exists(&base_abs.join(parent)).await {
                         return Map::serve(
                             &base_abs.join(parent),
                             &pathbuf
@@ -139,7 +140,7 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
             }
         } else {
             for parent in pathbuf.ancestors() {
-                if Map::exists(&base_abs.join(parent)).await {
+                if map_exists(&base_abs.join(parent)).await {
                     return Map::serve(
                         &base_abs.join(parent),
                         &pathbuf
@@ -155,7 +156,7 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
     }
 
     if pathbuf.iter().last().map(|s| s.to_str().unwrap()) == Some("map")
-        && Map::exists(
+        && map_exists(
             &pathbuf
                 .iter()
                 .take(pathbuf.iter().count() - 1)
@@ -166,8 +167,28 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
         return Map::serve(&pathbuf, std::path::Path::new(""), req).await;
     }
 
-    if Map::exists(&pathbuf).await {
-        return map(id, path, topbar).await;
+    if map_exists(&pathbuf).await {
+        let manifest = RenderManifest::load(&pathbuf).await;
+        let import = ImportManifest::load(&pathbuf).await;
+        let snapshots = if id == account.id {
+            list_snapshots(
+                account.id,
+                &std::path::Path::new("blue").join(&preview_path),
+            )
+            .await
+        } else {
+            Vec::new()
+        };
+        return map(
+            id,
+            path,
+            topbar,
+            id == account.id,
+            manifest,
+            import,
+            snapshots,
+        )
+        .await;
     }
 
     if matches!(path.as_str(), "Shared" | "Shared/") {
@@ -187,8 +208,126 @@ async fn fs_task(path: Path<String>, req: &HttpRequest) -> Result<HttpResponse,
     .await
 }
 
-async fn map(id: i64, path: String, topbar: Cow<'_, str>) -> Result<HttpResponse, Box<dyn Error>> {
+/// Serves a previous version of a map, `/snapshot/{id}/{map}/map` for the viewer.
+#[get("/snapshot/{id}/{path:.*}")]
+pub async fn snapshot(path: Path<(u64, String)>, req: HttpRequest) -> HttpResponse {
+    from_res(snapshot_task(path, &req).await, &req).await
+}
+
+async fn snapshot_task(
+    path: Path<(u64, String)>,
+    req: &HttpRequest,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    let (id, path) = path.into_inner();
+
+    let token_cookie = req.cookie("token");
+    let account = Account::v1_get_by_token(cookie_to_str(&token_cookie).unwrap_or_default())
+        .await?
+        .v1_restrict_verified()?;
+
+    let pathbuf = std::path::Path::new("blue").join(path.trim_start_matches('/'));
+    if has_dotdot(&pathbuf) {
+        return Err(V1Error::PermissionDenied.into());
+    }
+
+    for parent in pathbuf.ancestors() {
+        let Some(version) = snapshot_path(account.id, parent, id).await else {
+            continue;
+        };
+        if parent.iter().count() > 1 && map_exists(&version).await {
+            let sub = pathbuf
+                .iter()
+                .skip(parent.iter().count())
+                .collect::<PathBuf>();
+            let sub = if sub.as_os_str() == "map" {
+                PathBuf::new()
+            } else {
+                sub
+            };
+            return Map::serve(&version, &sub, req).await;
+        }
+    }
+
+    Err(V1BlueError::SnapshotNotFound.into())
+}
+
+async fn map(
+    id: i64,
+    path: String,
+    topbar: Cow<'_, str>,
+    owned: bool,
+    manifest: Option<RenderManifest>,
+    import: Option<ImportManifest>,
+    snapshots: Vec<Snapshot>,
+) -> Result<HttpResponse, Box<dyn Error>> {
     let path_escaped = html_escape::encode_safe(&path).to_string();
+    let rerender = match &manifest {
+        Some(manifest) if owned => format!(
+            r#"<button class="ghbutton" id="rerender" source="{}" target="{}">Re-render</button>"#,
+            html_escape::encode_double_quoted_attribute(&manifest.from.to_string_lossy()),
+            html_escape::encode_double_quoted_attribute(path.trim_matches('/'))
+        ),
+        _ => String::new(),
+    };
+    let watch = if owned && manifest.is_some() {
+        let state = MapWatch::find(id, &format!("blue/{}", path.trim_matches('/')))
+            .await?
+            .map(|watch| watch.state())
+            .unwrap_or(WatchState::Off);
+        let option = |value: WatchState| {
+            format!(
+                r#"<option value="{0}" {1}>{0}</option>"#,
+                value.as_str(),
+                if value == state { "selected" } else { "" }
+            )
+        };
+        format!(
+            r#"<label id="watch-label">Auto update <select id="watch" map="{}">{}{}{}</select></label>"#,
+            html_escape::encode_double_quoted_attribute(path.trim_matches('/')),
+            option(WatchState::Off),
+            option(WatchState::On),
+            option(WatchState::Paused),
+        )
+    } else {
+        String::new()
+    };
+    let export = if owned {
+        format!(
+            r#"<button class="ghbutton" id="export" map="{}">Export</button><span id="export-status"></span>"#,
+            html_escape::encode_double_quoted_attribute(path.trim_matches('/'))
+        )
+    } else {
+        String::new()
+    };
+    let versions = if snapshots.is_empty() {
+        String::new()
+    } else {
+        let options = snapshots.iter().fold(String::new(), |mut buf, version| {
+            write!(
+                buf,
+                r#"<option value="{}">{}</option>"#,
+                version.id,
+                version.date()
+            )
+            .unwrap();
+            buf
+        });
+        format!(
+            r#"<select id="versions" map="{0}"><option value="">Current version</option>{options}</select><button class="ghbutton dangerbut hide" id="delete-version">Delete snapshot</button><a href="/compare/{0}" id="compare-link">Compare versions</a>"#,
+            html_escape::encode_double_quoted_attribute(path.trim_matches('/'))
+        )
+    };
+    let description = manifest
+        .as_ref()
+        .map(RenderManifest::describe)
+        .or_else(|| import.as_ref().map(ImportManifest::describe))
+        .map(|description| {
+            format!(
+                r#"<span id="manifest">{}</span>"#,
+                html_escape::encode_text(&description)
+            )
+        })
+        .unwrap_or_default();
     let map_path_dirty = format!("/fs/{}/map", path.trim_matches('/'));
     let map_path = html_escape::encode_text(&map_path_dirty);
 
@@ -227,6 +366,13 @@ async fn map(id: i64, path: String, topbar: Cow<'_, str>) -> Result<HttpResponse
     {topbar}
 <div id="path-display">
     {path_display}
+</div>
+<div id="map-info">
+    {description}
+    {rerender}
+    {watch}
+    {export}
+    {versions}
 </div>
     <iframe id="viewer" src="{map_path}"></iframe> 
     <script src="/static/scripts/file.js" defer></script>
@@ -254,7 +400,7 @@ async fn dir(
     let base_abs = get_user_dir(account.id, None).join(&pathbuf);
 
     // for parent in pathbuf.ancestors().skip(1) {
-    //     if Map::exists(&base_abs.join(parent)).await {
+    //     if map_exists(&base_abs.join(parent)).await {
     //         return Ok(HttpResponse::TemporaryRedirect()
     //             .append_header(("Location", format!("/fs/{path}")))
     //             .await
@@ -277,7 +423,7 @@ async fn dir(
             continue;
         }
 
-        if Map::exists(&base_abs.join(&item.name)).await {
+        if map_exists(&base_abs.join(&item.name)).await {
             item.is_file = true;
             items.push(item);
         } else if !item.is_file {
@@ -308,6 +454,7 @@ async fn dir(
         .render()
         .await;
     let pathbuf_safe = html_escape::encode_safe(pathbuf.to_str().unwrap());
+    let world_panel = world_panel(&base_abs, id == account.id).await;
 
     let html = format!(
         r#"<!-- {{ "path": "{pathbuf_safe}", "id": {id} }} -->
@@ -362,6 +509,7 @@ async fn dir(
 <div id="path-display">
   {path_display}
 </div>
+  {world_panel}
   {items_display}
   <script src="/static/scripts/fs.js" defer></script>
   <script src="/static/scripts/topbar.js" defer></script>
@@ -375,3 +523,64 @@ async fn dir(
         // .insert_header(("Content-Security-Policy", csp_header))
         .body(html))
 }
+
+/// Summary of the world in `dir`, empty if it is not a world.
+async fn world_panel(dir: &std::path::Path, owner: bool) -> String {
+    let info = match world_info(dir, owner).await {
+        Ok(info) => info,
+        Err(V1BlueError::NotAWorld) => return String::new(),
+        Err(V1BlueError::InvalidLevelDat { reason }) => {
+            return format!(
+                r#"<div id="world-info"><h2>Minecraft world</h2><span class="world-error">level.dat could not be read: {}</span></div>"#,
+                html_escape::encode_safe(&reason)
+            )
+        }
+        Err(_) => return String::new(),
+    };
+
+    let level = &info.level;
+    let mut rows = Vec::new();
+    if let Some(version) = &level.version {
+        rows.push(("Version", version.clone()));
+    }
+    if let Some(data_version) = level.data_version {
+        rows.push(("Data version", data_version.to_string()));
+    }
+    if let Some(seed) = level.seed {
+        rows.push(("Seed", seed.to_string()));
+    }
+    if let Some([x, y, z]) = level.spawn {
+        rows.push(("Spawn", format!("{x}, {y}, {z}")));
+    }
+    if let Some(game_mode) = &level.game_mode {
+        let hardcore = if level.hardcore { " (hardcore)" } else { "" };
+        rows.push(("Game mode", format!("{game_mode}{hardcore}")));
+    }
+    if let Some(date) = level
+        .last_played
+        .and_then(chrono::DateTime::from_timestamp_millis)
+    {
+        rows.push(("Last played", date.format("%Y-%m-%d %H:%M UTC").to_string()));
+    }
+    for dimension in info.dimensions.iter() {
+        rows.push((
+            "Dimension",
+            format!("{} ({} regions)", dimension.dimension, dimension.regions),
+        ));
+    }
+
+    let rows = rows.iter().fold(String::new(), |mut buf, (label, value)| {
+        write!(
+            buf,
+            "<tr><td>{label}</td><td>{}</td></tr>",
+            html_escape::encode_safe(value)
+        )
+        .unwrap();
+        buf
+    });
+
+    format!(
+        r#"<div id="world-info"><h2>{}</h2><table>{rows}</table></div>"#,
+        html_escape::encode_safe(level.name.as_deref().unwrap_or("Minecraft world"))
+    )
+}
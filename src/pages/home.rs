@@ -5,7 +5,7 @@ use goodmorning_services::{
     structs::{Account, GMServices},
 };
 
-use crate::{functions::internalserver_error, intererr, values::BLUE_CONFIG};
+use crate::{functions::internalserver_error, intererr, values::VALUES};
 
 #[get("/")]
 pub async fn home(req: HttpRequest) -> HttpResponse {
@@ -14,7 +14,7 @@ pub async fn home(req: HttpRequest) -> HttpResponse {
 
     if token.is_none() {
         return intererr!(NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/login.html")
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html")
         )
         .await
         .map(|file| file.into_response(&req)));
@@ -24,7 +24,7 @@ pub async fn home(req: HttpRequest) -> HttpResponse {
         Ok(Some(account)) => account,
         Ok(None) => {
             return match NamedFile::open_async(
-                std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path)
+                std::path::Path::new(&VALUES.get().config.static_path)
                     .join("html/been-loggedout.html"),
             )
             .await
@@ -43,8 +43,7 @@ pub async fn home(req: HttpRequest) -> HttpResponse {
         .contains(&GMServices::Blue.as_str().to_string())
     {
         return match NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path)
-                .join("html/finish-setup.html"),
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/finish-setup.html"),
         )
         .await
         {
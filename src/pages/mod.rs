@@ -6,3 +6,6 @@ pub use fs::*;
 #[allow(hidden_glob_reexports)]
 mod render;
 pub use render::*;
+#[allow(hidden_glob_reexports)]
+mod compare;
+pub use compare::*;
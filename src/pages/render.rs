@@ -1,9 +1,9 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::{error::Error, path::PathBuf};
 
 use actix_files::NamedFile;
 use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
-use bluemap_singleserve::Map;
 use goodmorning_services::bindings::services::v1::AccessType;
 use goodmorning_services::structs::Account;
 use goodmorning_services::{
@@ -16,14 +16,20 @@ use tokio::fs;
 
 use crate::{
     components::topbar_from_req,
-    functions::from_res,
-    values::{BLUE_CONFIG, PRESETS},
+    functions::{
+        dimension_name, from_res, gen_nonce, map_exists, region_dir, user_preset_info,
+        user_presets, OverrideKind, END, NETHER, OVERWORLD, PRESET_OVERRIDES, USER_PRESET_PREFIX,
+    },
+    structs::RenderManifest,
+    values::VALUES,
 };
 
 #[derive(Serialize, Deserialize)]
 struct Query {
     target: String,
     source: String,
+    #[serde(default)]
+    update: bool,
 }
 
 #[get("/render")]
@@ -40,7 +46,7 @@ async fn render_task(
 
     if token.is_none() {
         return Ok(NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/login.html"),
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
         )
         .await
         .map(|file| file.into_response(req))?);
@@ -55,7 +61,7 @@ async fn render_task(
         account
     } else {
         return Ok(NamedFile::open_async(
-            std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path).join("html/login.html"),
+            std::path::Path::new(&VALUES.get().config.static_path).join("html/login.html"),
         )
         .await?
         .into_response(req));
@@ -92,7 +98,10 @@ async fn render_task(
             .collect();
     }
 
-    if Map::exists(&get_user_dir(account.id, Some(GMServices::Blue)).join(&target_path)).await {
+    let target_is_map =
+        map_exists(&get_user_dir(account.id, Some(GMServices::Blue)).join(&target_path)).await;
+
+    if target_is_map && !query.update {
         return Ok(HttpResponse::TemporaryRedirect()
             .insert_header((
                 "Location",
@@ -106,17 +115,24 @@ async fn render_task(
         return Err(V1Error::FileNotFound.into());
     }
 
-    if fs::try_exists(get_user_dir(account.id, Some(GMServices::Blue)).join(&target_path)).await? {
+    if query.update {
+        if !target_is_map {
+            return Err(V1Error::FileNotFound.into());
+        }
+    } else if fs::try_exists(get_user_dir(account.id, Some(GMServices::Blue)).join(&target_path))
+        .await?
+    {
         return Err(V1Error::PathOccupied.into());
     }
 
     let source_safe = html_escape::encode_safe(query.source.trim_matches('/'));
     let target_safe = html_escape::encode_safe(query.target.trim_matches('/'));
 
-    let selected = &BLUE_CONFIG.get().unwrap().default_preset;
-    let all_presets = PRESETS
-        .get()
-        .unwrap()
+    let values = VALUES.get();
+    let config = &values.config;
+    let selected = &config.default_preset;
+    let all_presets = values
+        .presets
         .iter()
         .fold(String::new(), |mut buf, current| {
             write!(
@@ -127,6 +143,127 @@ async fn render_task(
             .unwrap();
             buf
         });
+    let user_presets = user_presets(account.id).await;
+
+    let mut preset_info = values
+        .preset_info
+        .iter()
+        .map(|info| (info.preset.clone(), info.clone()))
+        .collect::<HashMap<_, _>>();
+    for name in user_presets.iter() {
+        let info = user_preset_info(account.id, name).await;
+        preset_info.insert(info.preset.clone(), info);
+    }
+    let preset_info = serde_json::to_string(&preset_info)?.replace("</", "<\\/");
+    let nonce = gen_nonce();
+
+    let user_presets = if user_presets.is_empty() {
+        String::new()
+    } else {
+        let options = user_presets.iter().fold(String::new(), |mut buf, current| {
+            let current = html_escape::encode_double_quoted_attribute(current);
+            write!(
+                buf,
+                r#"<option value="{USER_PRESET_PREFIX}{current}">{current}</option>"#
+            )
+            .unwrap();
+            buf
+        });
+        format!(r#"<optgroup label="Your presets">{options}</optgroup>"#)
+    };
+
+    let source_abs = get_user_dir(account.id, None).join(&source_path);
+    let mut dimensions = Vec::new();
+    for dimension in [OVERWORLD, NETHER, END] {
+        if fs::try_exists(region_dir(&source_abs, dimension)).await? {
+            dimensions.push(dimension);
+        }
+    }
+
+    // only worth choosing if there is more than one, otherwise the preset's dimension is rendered
+    let dimensions = if dimensions.len() > 1 {
+        let options = dimensions.iter().fold(String::new(), |mut buf, dimension| {
+            write!(
+                buf,
+                r#"<label><input type="checkbox" class="dimension" value="{dimension}" /> {}</label>"#,
+                dimension_name(dimension)
+            )
+            .unwrap();
+            buf
+        });
+        format!(
+            r#"<div id="dimensions">
+          <h2>Dimensions</h2>
+          <span>Leave unchecked to render the preset's dimension only.</span>
+          {options}
+        </div>"#
+        )
+    } else {
+        String::new()
+    };
+
+    let manifest = if query.update {
+        RenderManifest::load(&get_user_dir(account.id, Some(GMServices::Blue)).join(&target_path))
+            .await
+    } else {
+        None
+    };
+
+    // an update sends every override, filled in with the map's current ones so clearing one
+    // takes it off the map
+    let overrides = PRESET_OVERRIDES
+        .iter()
+        .fold(String::new(), |mut buf, (key, kind)| {
+            let current = manifest
+                .as_ref()
+                .and_then(|manifest| manifest.overrides.get(*key));
+            // the manifest is a file the user can edit
+            let value = match current {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            };
+            let value = html_escape::encode_double_quoted_attribute(&value);
+            let selected = |option: &str| if value == option { "selected" } else { "" };
+
+            let input = match kind {
+                OverrideKind::Bool => format!(
+                    r#"<select class="override" name="{key}" data-kind="bool"><option value="">preset default</option><option value="true" {}>on</option><option value="false" {}>off</option></select>"#,
+                    selected("true"),
+                    selected("false")
+                ),
+                OverrideKind::Integer { min, max } => format!(
+                    r#"<input type="number" class="override" name="{key}" data-kind="number" min="{min}" max="{max}" step="1" value="{value}" />"#
+                ),
+                OverrideKind::Number { min, max } => format!(
+                    r#"<input type="number" class="override" name="{key}" data-kind="number" min="{min}" max="{max}" step="any" value="{value}" />"#
+                ),
+                OverrideKind::Color => format!(
+                    r##"<input type="text" class="override" name="{key}" data-kind="color" placeholder="#rrggbb" value="{value}" />"##
+                ),
+            };
+            write!(buf, "<label>{key} {input}</label>").unwrap();
+            buf
+        });
+
+    // only offered when re-rendering, snapshots are of the map being replaced
+    let keep_versions = if query.update && config.snapshot_limit(&account.limit) > 0 {
+        let keep = manifest
+            .as_ref()
+            .is_some_and(|manifest| manifest.keep_versions);
+        format!(
+            r#"<label id="keep-versions-label"><input type="checkbox" id="keep-versions" {} /> Keep the current version as a snapshot</label>"#,
+            if keep { "checked" } else { "" }
+        )
+    } else {
+        String::new()
+    };
+
+    let render_label = if query.update {
+        "Update BlueMap"
+    } else {
+        "Render to BlueMap"
+    };
 
     let html = format!(
         r#"
@@ -170,21 +307,51 @@ async fn render_task(
         <h1>Choose a preset</h1>
         <select id="preset" selected="{selected}">
             {all_presets}
+            {user_presets}
         </select>
+        <div id="preset-info">
+          <p id="preset-description"></p>
+          <span id="preset-details"></span>
+          <img id="preset-preview" class="hide" alt="" />
+        </div>
+        {dimensions}
+        <details id="area">
+          <summary>Render area</summary>
+          <span>Leave empty to render the whole world. Use either a bounding box or a center and radius.</span>
+          <label>X <input type="number" class="area" name="min_x" placeholder="min" /> to <input type="number" class="area" name="max_x" placeholder="max" /></label>
+          <label>Z <input type="number" class="area" name="min_z" placeholder="min" /> to <input type="number" class="area" name="max_z" placeholder="max" /></label>
+          <label>Center <input type="number" class="area" name="center_x" placeholder="x" /> <input type="number" class="area" name="center_z" placeholder="z" /></label>
+          <label>Radius <input type="number" class="area" name="radius" min="0" placeholder="blocks" /></label>
+          <label>Y <input type="number" class="area" name="min_y" placeholder="min" /> to <input type="number" class="area" name="max_y" placeholder="max" /></label>
+        </details>
+        <details id="overrides">
+          <summary>Advanced options</summary>
+          <span>Changes the preset for this render only, leave empty to keep the preset's value.</span>
+          {overrides}
+        </details>
       </div>
       <div id="right">
         <h1>Start rendering</h1>
-        <button class="ghbutton" id="render">Render to BlueMap</button>
+        <span id="estimate" class="hide"></span>
+        <span id="estimate-warning" class="hide"></span>
+        {keep_versions}
+        <button class="ghbutton" id="render">{render_label}</button>
         <br />
         <br />
         <span id="timer" class="hide"></span>
+        <progress id="progress" class="hide" value="0" max="1"></progress>
+        <span id="eta" class="hide"></span>
+        <span id="queue" class="hide"></span>
         <span id="failed" class="hide"></span>
         <span id="success" class="hide"></span>
         <br />
+        <button class="ghbutton dangerbut hide" id="cancel">Cancel render</button>
         <button class="ghbutton dangerbut hide" id="viewerror">View error message</button>
         <button class="ghbutton hide" id="reload">Reload page</button>
+        <pre id="log" class="hide"></pre>
       </div>
     </div>
+    <script nonce="{nonce}">var presetInfo = {preset_info};</script>
     <script src="/static/scripts/render.js" defer></script>
   </body>
 </html>"#,
@@ -2,15 +2,14 @@ use actix_files::NamedFile;
 use actix_web::{get, web::Path, Result};
 use goodmorning_services::SERVICES_STATIC;
 
-use crate::values::BLUE_CONFIG;
+use crate::values::VALUES;
 
 #[get("/static/{path:.*}")]
 pub async fn r#static(params: Path<String>) -> Result<NamedFile> {
     let params = params.into_inner();
 
     Ok(NamedFile::open_async(
-        std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path)
-            .join(params.trim_start_matches('/')),
+        std::path::Path::new(&VALUES.get().config.static_path).join(params.trim_start_matches('/')),
     )
     .await?)
 }
@@ -31,8 +30,7 @@ pub async fn static_services(params: Path<String>) -> Result<NamedFile> {
 #[get("/remindverify")]
 pub async fn remindverify() -> Result<NamedFile> {
     Ok(NamedFile::open_async(
-        std::path::Path::new(&BLUE_CONFIG.get().unwrap().static_path)
-            .join("html/remindverify.html"),
+        std::path::Path::new(&VALUES.get().config.static_path).join("html/remindverify.html"),
     )
     .await?)
 }
@@ -0,0 +1,54 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+/// Blocks along one side of a region file.
+const REGION_SIZE: i64 = 512;
+
+/// Part of the world a render is limited to, unbounded on every side left as `None`.
+///
+/// Passed to BlueMap as the `min-x`/`max-x`/`min-z`/`max-z`/`min-y`/`max-y` map settings.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderArea {
+    pub min_x: Option<i32>,
+    pub max_x: Option<i32>,
+    pub min_z: Option<i32>,
+    pub max_z: Option<i32>,
+    pub min_y: Option<i32>,
+    pub max_y: Option<i32>,
+}
+
+impl RenderArea {
+    pub fn is_unbounded(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether any part of region file `r.<x>.<z>.mca` is inside the area.
+    pub fn contains_region(&self, x: i32, z: i32) -> bool {
+        let (x, z) = (x as i64, z as i64);
+        let overlaps = |min: Option<i32>, max: Option<i32>, region: i64| {
+            let (start, end) = (region * REGION_SIZE, region * REGION_SIZE + REGION_SIZE - 1);
+            min.is_none_or(|min| end >= min as i64) && max.is_none_or(|max| start <= max as i64)
+        };
+
+        overlaps(self.min_x, self.max_x, x) && overlaps(self.min_z, self.max_z, z)
+    }
+
+    /// Preset lines limiting the render to the area, empty if unbounded.
+    pub fn preset_overrides(&self) -> String {
+        [
+            ("min-x", self.min_x),
+            ("max-x", self.max_x),
+            ("min-z", self.min_z),
+            ("max-z", self.max_z),
+            ("min-y", self.min_y),
+            ("max-y", self.max_y),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .fold(String::new(), |mut buf, (key, value)| {
+            writeln!(buf, "{key}: {value}").unwrap();
+            buf
+        })
+    }
+}
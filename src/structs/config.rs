@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use goodmorning_services::{traits::ConfigTrait, LogOptions};
@@ -23,6 +25,77 @@ pub struct BlueConfig {
     pub alternate_pfp: Option<String>,
     #[serde(default = "default_preset_default")]
     pub default_preset: String,
+    /// Account ids allowed to reload the config through the API.
+    #[serde(default)]
+    pub admins: Vec<i64>,
+    /// Bytes each account may use, render estimates warn when a map would go over it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage_quota: Option<u64>,
+    /// `storage_quota` for accounts of a limit tier, as in `QUEUE_PRESETS`.
+    #[serde(default)]
+    pub storage_quotas: HashMap<String, u64>,
+    /// Bytes an archive may extract to when rendered from directly.
+    #[serde(default = "archive_max_size_default")]
+    pub archive_max_size: u64,
+    /// Previous versions kept per map when rendering with `keep_versions`.
+    #[serde(default = "snapshot_limit_default")]
+    pub snapshot_limit: usize,
+    /// `snapshot_limit` for accounts of a limit tier, as in `QUEUE_PRESETS`.
+    #[serde(default)]
+    pub snapshot_limits: HashMap<String, usize>,
+    /// Seconds between checks of watched maps' worlds for changes.
+    #[serde(default = "watch_interval_default")]
+    pub watch_interval: u64,
+    /// Seconds a watched world must go unchanged before its map is updated, so a world still being
+    /// synced is not rendered half way.
+    #[serde(default = "watch_debounce_default")]
+    pub watch_debounce: u64,
+    /// Times a job a restart interrupted is queued again before it is marked failed.
+    #[serde(default = "interrupted_retries_default")]
+    pub interrupted_retries: u32,
+    /// Jobs running at once across every account, unlimited if not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_renders: Option<usize>,
+    /// Share of `max_renders` an account of a limit tier gets while others wait, relative to the
+    /// other tiers, 1 for tiers not listed.
+    #[serde(default)]
+    pub tier_weights: HashMap<String, u32>,
+    /// BlueMap webapp files imported maps are served with, such as the `web` folder of a map
+    /// rendered on this server. Imports are refused if not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webapp_path: Option<String>,
+    /// Log file BlueMap writes to, `log.file` in its core.conf. Lines written to it during a
+    /// render are copied into the render's job log. Only allowed with `max_renders` set to 1, the
+    /// file is shared by every render and a job's log is shown to its owner.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bluemap_log: Option<String>,
+}
+
+impl BlueConfig {
+    /// Bytes an account on limit tier `limit` may use, if limited.
+    pub fn storage_quota(&self, limit: &str) -> Option<u64> {
+        self.storage_quotas
+            .get(limit)
+            .copied()
+            .or(self.storage_quota)
+    }
+
+    /// Previous versions an account on limit tier `limit` may keep per map.
+    pub fn snapshot_limit(&self, limit: &str) -> usize {
+        self.snapshot_limits
+            .get(limit)
+            .copied()
+            .unwrap_or(self.snapshot_limit)
+    }
+
+    /// Scheduling weight of accounts on limit tier `limit`.
+    pub fn tier_weight(&self, limit: &str) -> u32 {
+        self.tier_weights.get(limit).copied().unwrap_or(1)
+    }
 }
 
 fn allow_create_default() -> bool {
@@ -52,6 +125,19 @@ impl Default for BlueConfig {
             alternate_pfp: None,
             default_preset: default_preset_default(),
             render_timeout: render_timeout_default(),
+            admins: Vec::new(),
+            storage_quota: None,
+            storage_quotas: HashMap::new(),
+            archive_max_size: archive_max_size_default(),
+            snapshot_limit: snapshot_limit_default(),
+            snapshot_limits: HashMap::new(),
+            watch_interval: watch_interval_default(),
+            watch_debounce: watch_debounce_default(),
+            interrupted_retries: interrupted_retries_default(),
+            max_renders: None,
+            tier_weights: HashMap::new(),
+            webapp_path: None,
+            bluemap_log: None,
         }
     }
 }
@@ -98,3 +184,23 @@ fn default_preset_default() -> String {
 fn render_timeout_default() -> u64 {
     900
 }
+
+fn archive_max_size_default() -> u64 {
+    16 * 1024 * 1024 * 1024
+}
+
+fn snapshot_limit_default() -> usize {
+    5
+}
+
+fn watch_interval_default() -> u64 {
+    60
+}
+
+fn watch_debounce_default() -> u64 {
+    600
+}
+
+fn interrupted_retries_default() -> u32 {
+    1
+}
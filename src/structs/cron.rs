@@ -0,0 +1,364 @@
+use chrono::{DateTime, Datelike, NaiveDate, Timelike};
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+/// How far ahead the next run is looked for, so an expression like `0 0 31 2 *` ends the search.
+const SEARCH_DAYS: usize = 5 * 366;
+
+/// A cron expression, `minute hour day-of-month month day-of-week`, evaluated in UTC.
+///
+/// Also accepts `@hourly`, `@daily`, `@weekly`, `@monthly`, and the phrases `daily at 04:00`,
+/// `weekly on monday at 04:00`, `every 30 minutes` and `every 6 hours`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and day of week were both restricted, a day matching either runs.
+    either_day: bool,
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim().to_lowercase();
+        let expr = phrase(&expr)?.unwrap_or(expr);
+
+        let [minute, hour, day, month, weekday] = expr.split_whitespace().collect::<Vec<_>>()[..]
+        else {
+            return Err(
+                "expected 5 fields: minute hour day-of-month month day-of-week".to_string(),
+            );
+        };
+
+        let mut weekdays =
+            field(weekday, 0, 7, &WEEKDAYS).map_err(|e| format!("day of week: {e}"))?;
+        // both 0 and 7 are sunday
+        if weekdays & 1 << 7 != 0 {
+            weekdays = weekdays & !(1 << 7) | 1;
+        }
+
+        Ok(Self {
+            minutes: field(minute, 0, 59, &[]).map_err(|e| format!("minute: {e}"))?,
+            hours: field(hour, 0, 23, &[]).map_err(|e| format!("hour: {e}"))?,
+            days: field(day, 1, 31, &[]).map_err(|e| format!("day of month: {e}"))?,
+            months: field(month, 1, 12, &MONTHS).map_err(|e| format!("month: {e}"))?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & 1 << date.day() != 0;
+        let weekday = self.weekdays & 1 << date.weekday().num_days_from_sunday() != 0;
+
+        self.months & 1 << date.month() != 0
+            && if self.either_day {
+                day || weekday
+            } else {
+                day && weekday
+            }
+    }
+
+    /// First time the expression matches strictly after `after`, both in seconds since epoch.
+    pub fn next_after(&self, after: u64) -> Option<u64> {
+        let start = DateTime::from_timestamp((after / 60 + 1) as i64 * 60, 0)?;
+        let mut date = start.date_naive();
+
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_day(date) {
+                let (from_hour, from_minute) = if date == start.date_naive() {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+
+                for hour in (from_hour..24).filter(|hour| self.hours & 1 << hour != 0) {
+                    let from = if hour == from_hour { from_minute } else { 0 };
+                    if let Some(minute) = (from..60).find(|minute| self.minutes & 1 << minute != 0)
+                    {
+                        return Some(
+                            date.and_hms_opt(hour, minute, 0)?.and_utc().timestamp() as u64
+                        );
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+}
+
+/// Turns the phrases `Cron` accepts into cron expressions, `None` if `expr` is not one.
+fn phrase(expr: &str) -> Result<Option<String>, String> {
+    let words = expr.split_whitespace().collect::<Vec<_>>();
+
+    let cron = match words[..] {
+        ["@hourly" | "hourly"] => "0 * * * *".to_string(),
+        ["@daily" | "@midnight" | "daily"] => "0 0 * * *".to_string(),
+        ["@weekly" | "weekly"] => "0 0 * * 0".to_string(),
+        ["@monthly" | "monthly"] => "0 0 1 * *".to_string(),
+        ["daily", "at", time] => {
+            let (hour, minute) = time_of_day(time)?;
+            format!("{minute} {hour} * * *")
+        }
+        ["weekly", "on", day, "at", time] => {
+            let (hour, minute) = time_of_day(time)?;
+            let day = WEEKDAYS
+                .iter()
+                .position(|name| day.starts_with(name))
+                .ok_or_else(|| format!("{day} is not a day of the week"))?;
+            format!("{minute} {hour} * * {day}")
+        }
+        ["every", n, "minute" | "minutes"] => format!("*/{} * * * *", every(n, 59)?),
+        ["every", n, "hour" | "hours"] => format!("0 */{} * * *", every(n, 23)?),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(cron))
+}
+
+/// `04:00` as hour and minute.
+fn time_of_day(time: &str) -> Result<(u32, u32), String> {
+    time.split_once(':')
+        .and_then(|(hour, minute)| Some((hour.parse().ok()?, minute.parse().ok()?)))
+        .filter(|(hour, minute)| *hour < 24 && *minute < 60)
+        .ok_or_else(|| format!("{time} is not a time of day, use hh:mm"))
+}
+
+fn every(n: &str, max: u32) -> Result<u32, String> {
+    n.parse()
+        .ok()
+        .filter(|n| (1..=max).contains(n))
+        .ok_or_else(|| format!("{n} is not between 1 and {max}"))
+}
+
+/// Parses one field of a cron expression into a bit per value, e.g. `1-5`, `*/15` or `mon,fri`.
+fn field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let value = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .or_else(|| {
+                names
+                    .iter()
+                    .position(|name| *name == value)
+                    .map(|i| i as u32 + min)
+            })
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("{value} is not between {min} and {max}"))
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<usize>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("{step} is not a valid step"))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // `5/15` runs from 5 to the end
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+
+        if start > end {
+            return Err(format!("{start}-{end} is an empty range"));
+        }
+
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    /// `time` given as `yyyy-mm-dd hh:mm` UTC, in seconds since epoch.
+    fn at(time: &str) -> u64 {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64
+    }
+
+    fn next(expr: &str, after: &str) -> Option<u64> {
+        Cron::parse(expr).unwrap().next_after(at(after))
+    }
+
+    fn bits(values: &[u32]) -> u64 {
+        values.iter().fold(0, |bits, value| bits | 1 << value)
+    }
+
+    #[test]
+    fn field_ranges_steps_and_names() {
+        assert_eq!(field("*", 0, 6, &[]), Ok(bits(&[0, 1, 2, 3, 4, 5, 6])));
+        assert_eq!(field("1-5", 0, 59, &[]), Ok(bits(&[1, 2, 3, 4, 5])));
+        assert_eq!(field("*/15", 0, 59, &[]), Ok(bits(&[0, 15, 30, 45])));
+        assert_eq!(field("5/20", 0, 59, &[]), Ok(bits(&[5, 25, 45])));
+        assert_eq!(field("10-20/5", 0, 59, &[]), Ok(bits(&[10, 15, 20])));
+        assert_eq!(field("1,3,7", 0, 59, &[]), Ok(bits(&[1, 3, 7])));
+        assert_eq!(field("mon,fri", 0, 7, &WEEKDAYS), Ok(bits(&[1, 5])));
+        assert_eq!(field("mon-wed", 0, 7, &WEEKDAYS), Ok(bits(&[1, 2, 3])));
+        assert_eq!(field("jan,dec", 1, 12, &MONTHS), Ok(bits(&[1, 12])));
+
+        for invalid in ["60", "5-1", "*/0", "*/x", "foo", "", "-1"] {
+            assert!(field(invalid, 0, 59, &[]).is_err(), "{invalid}");
+        }
+        assert!(field("0", 1, 31, &[]).is_err());
+        assert!(field("monday", 0, 7, &WEEKDAYS).is_err());
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        assert_eq!(Cron::parse("0 0 * * 7"), Cron::parse("0 0 * * 0"));
+        assert_eq!(Cron::parse("0 0 * * 7").unwrap().weekdays, 1);
+        assert_eq!(
+            Cron::parse("0 0 * * 5-7").unwrap().weekdays,
+            bits(&[0, 5, 6])
+        );
+        // 2026-10-18 is a sunday
+        assert_eq!(
+            next("0 0 * * 7", "2026-10-14 12:00"),
+            Some(at("2026-10-18 00:00"))
+        );
+    }
+
+    #[test]
+    fn either_day() {
+        // both restricted, the 13th and every friday run
+        assert!(Cron::parse("0 0 13 * fri").unwrap().either_day);
+        assert_eq!(
+            next("0 0 13 * fri", "2026-10-01 00:00"),
+            Some(at("2026-10-02 00:00"))
+        );
+        assert_eq!(
+            next("0 0 13 * fri", "2026-10-10 00:00"),
+            Some(at("2026-10-13 00:00"))
+        );
+
+        // only one restricted, the other one is not a match on its own
+        assert!(!Cron::parse("0 0 13 * *").unwrap().either_day);
+        assert!(!Cron::parse("0 0 * * fri").unwrap().either_day);
+        assert_eq!(
+            next("0 0 13 * *", "2026-10-01 00:00"),
+            Some(at("2026-10-13 00:00"))
+        );
+        assert_eq!(
+            next("0 0 * * fri", "2026-10-10 00:00"),
+            Some(at("2026-10-16 00:00"))
+        );
+    }
+
+    #[test]
+    fn next_after_boundaries() {
+        // strictly after, within the hour and across it
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-18 12:00"),
+            Some(at("2026-10-18 12:15"))
+        );
+        assert_eq!(
+            next("0 * * * *", "2026-10-18 12:30"),
+            Some(at("2026-10-18 13:00"))
+        );
+        assert_eq!(
+            next("0 * * * *", "2026-10-18 13:00"),
+            Some(at("2026-10-18 14:00"))
+        );
+        assert_eq!(
+            Cron::parse("0 * * * *")
+                .unwrap()
+                .next_after(at("2026-10-18 12:59") + 59),
+            Some(at("2026-10-18 13:00"))
+        );
+
+        // across days, months and years
+        assert_eq!(
+            next("30 4 * * *", "2026-10-18 05:00"),
+            Some(at("2026-10-19 04:30"))
+        );
+        assert_eq!(
+            next("0 0 * * *", "2026-10-31 23:59"),
+            Some(at("2026-11-01 00:00"))
+        );
+        assert_eq!(
+            next("0 0 1 * *", "2026-10-18 00:00"),
+            Some(at("2026-11-01 00:00"))
+        );
+        assert_eq!(
+            next("0 0 31 * *", "2026-11-01 00:00"),
+            Some(at("2026-12-31 00:00"))
+        );
+        assert_eq!(
+            next("0 0 1 1 *", "2026-10-18 00:00"),
+            Some(at("2027-01-01 00:00"))
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2026-10-18 00:00"),
+            Some(at("2028-02-29 00:00"))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_run() {
+        assert_eq!(next("0 0 31 2 *", "2026-10-18 00:00"), None);
+        assert_eq!(next("0 0 30 feb *", "2026-10-18 00:00"), None);
+        assert_eq!(next("0 0 31 apr,jun,sep,nov *", "2026-10-18 00:00"), None);
+    }
+
+    #[test]
+    fn phrases() {
+        for (phrase, expr) in [
+            ("@hourly", "0 * * * *"),
+            ("hourly", "0 * * * *"),
+            ("@daily", "0 0 * * *"),
+            ("@midnight", "0 0 * * *"),
+            ("daily", "0 0 * * *"),
+            ("@weekly", "0 0 * * 0"),
+            ("weekly", "0 0 * * 0"),
+            ("@monthly", "0 0 1 * *"),
+            ("monthly", "0 0 1 * *"),
+            ("daily at 04:30", "30 4 * * *"),
+            ("Daily at 23:05", "5 23 * * *"),
+            ("weekly on monday at 04:00", "0 4 * * 1"),
+            ("weekly on sat at 12:15", "15 12 * * 6"),
+            ("every 30 minutes", "*/30 * * * *"),
+            ("every 1 minute", "*/1 * * * *"),
+            ("every 6 hours", "0 */6 * * *"),
+            ("every 1 hour", "0 */1 * * *"),
+        ] {
+            assert_eq!(Cron::parse(phrase), Cron::parse(expr), "{phrase}");
+        }
+
+        for invalid in [
+            "daily at 24:00",
+            "daily at 4",
+            "weekly on funday at 04:00",
+            "every 0 minutes",
+            "every 60 minutes",
+            "every 24 hours",
+            "every x hours",
+            "0 0 * *",
+        ] {
+            assert!(Cron::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::{functions::now, values::RENDER_HISTORY};
+
+/// Renders looked at when working out rates.
+const HISTORY_SAMPLES: i64 = 50;
+/// Renders searched for ones of a similar size.
+const SIMILAR_SAMPLES: i64 = 500;
+/// Assumed size of a render whose size is not known, before the server has history.
+const DEFAULT_REGIONS: u64 = 64;
+/// Used until the server has finished a render of its own.
+const DEFAULT_SECONDS_PER_REGION: f64 = 8.;
+const DEFAULT_BYTES_PER_REGION: f64 = 512. * 1024.;
+/// Seconds `RenderHistory::recent` is reused for, every open render page polls the queue.
+const RECENT_HISTORY_TTL: u64 = 30;
+
+/// Last `RenderHistory::recent`, with when it was loaded.
+static RECENT_HISTORY: Mutex<Option<(u64, Arc<Vec<RenderRecord>>)>> = Mutex::new(None);
+
+/// A finished render, kept so later renders can be estimated from it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RenderRecord {
+    pub user: i64,
+    /// Regions actually rendered, only the changed ones for an update.
+    pub regions: u64,
+    /// In seconds, from leaving the queue to finishing.
+    pub duration: u64,
+    /// Size of the map output in bytes.
+    pub size: u64,
+    pub update: bool,
+    /// In seconds since epoch.
+    pub finished: u64,
+}
+
+impl RenderRecord {
+    pub async fn save(&self) -> Result<(), Box<dyn Error>> {
+        RENDER_HISTORY.get().unwrap().insert_one(self).await?;
+        Ok(())
+    }
+}
+
+/// Per region render cost on this server.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct RenderRates {
+    pub seconds_per_region: f64,
+    pub bytes_per_region: f64,
+    /// Renders the rates are based on, 0 if they are defaults.
+    pub samples: usize,
+}
+
+impl RenderRates {
+    /// Averages the most recent renders, falling back to defaults without history.
+    pub async fn recent() -> Self {
+        Self::from_records(&history(HISTORY_SAMPLES).await)
+    }
+
+    fn from_records(records: &[RenderRecord]) -> Self {
+        let regions = records.iter().map(|record| record.regions).sum::<u64>();
+        let seconds_per_region = if regions == 0 {
+            DEFAULT_SECONDS_PER_REGION
+        } else {
+            records.iter().map(|record| record.duration).sum::<u64>() as f64 / regions as f64
+        };
+
+        // an update's output holds the whole map, not just the regions it rendered
+        let (full_regions, full_size) = records
+            .iter()
+            .filter(|record| !record.update)
+            .fold((0, 0), |(regions, size), record| {
+                (regions + record.regions, size + record.size)
+            });
+        let bytes_per_region = if full_regions == 0 {
+            DEFAULT_BYTES_PER_REGION
+        } else {
+            full_size as f64 / full_regions as f64
+        };
+
+        Self {
+            seconds_per_region,
+            bytes_per_region,
+            samples: records.len(),
+        }
+    }
+}
+
+/// The most recent renders that rendered anything, newest first.
+async fn history(limit: i64) -> Vec<RenderRecord> {
+    let mut records = Vec::new();
+    if let Ok(mut cursor) = RENDER_HISTORY
+        .get()
+        .unwrap()
+        .find(doc! { "regions": { "$gt": 0 } })
+        .sort(doc! { "finished": -1 })
+        .limit(limit)
+        .await
+    {
+        while let Some(Ok(record)) = cursor.next().await {
+            records.push(record);
+        }
+    }
+    records
+}
+
+/// Recent renders, loaded once to estimate the duration of many jobs.
+pub struct RenderHistory(Arc<Vec<RenderRecord>>);
+
+impl RenderHistory {
+    /// Loaded at most once every `RECENT_HISTORY_TTL` seconds.
+    pub async fn recent() -> Self {
+        if let Some((loaded, records)) = RECENT_HISTORY.lock().unwrap().as_ref() {
+            if now() < loaded + RECENT_HISTORY_TTL {
+                return Self(records.clone());
+            }
+        }
+
+        let records = Arc::new(history(SIMILAR_SAMPLES).await);
+        *RECENT_HISTORY.lock().unwrap() = Some((now(), records.clone()));
+        Self(records)
+    }
+
+    /// Expected seconds to render `regions` regions, at the rate of renders between half and
+    /// twice that size, or of every recent render if there were none.
+    ///
+    /// Without a region count, the average duration of recent renders.
+    pub fn seconds(&self, regions: Option<u64>) -> u64 {
+        let Some(regions) = regions else {
+            return match self.0.len() {
+                0 => (DEFAULT_REGIONS as f64 * DEFAULT_SECONDS_PER_REGION) as u64,
+                len => self.0.iter().map(|record| record.duration).sum::<u64>() / len as u64,
+            };
+        };
+
+        let similar = self
+            .0
+            .iter()
+            .filter(|record| record.regions * 2 >= regions && record.regions <= regions * 2)
+            .take(HISTORY_SAMPLES as usize)
+            .cloned()
+            .collect::<Vec<_>>();
+        let rates = if similar.is_empty() {
+            RenderRates::from_records(&self.0[..self.0.len().min(HISTORY_SAMPLES as usize)])
+        } else {
+            RenderRates::from_records(&similar)
+        };
+
+        (regions as f64 * rates.seconds_per_region).ceil() as u64
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DimensionEstimate {
+    pub dimension: String,
+    pub regions: u64,
+    pub chunks: u64,
+}
+
+/// What a render is expected to take, as returned by a dry run.
+#[derive(Serialize, Clone, Debug)]
+pub struct RenderEstimate {
+    pub dimensions: Vec<DimensionEstimate>,
+    pub regions: u64,
+    pub chunks: u64,
+    /// Expected render duration in seconds.
+    pub seconds: u64,
+    /// Expected output size in bytes.
+    pub size: u64,
+    pub rates: RenderRates,
+    /// `render_timeout` in seconds.
+    pub timeout: u64,
+    pub exceeds_timeout: bool,
+    /// Storage quota and current usage in bytes, if the server sets a quota.
+    pub quota: Option<u64>,
+    pub used: Option<u64>,
+    pub exceeds_quota: bool,
+}
+
+/// Where a queued job stands and when it is expected to run, assuming every job ahead takes as
+/// long as renders of its size have taken before.
+#[derive(Serialize, Clone, Debug)]
+pub struct QueueEstimate {
+    /// 1 for the next job to start, `None` once the job is running.
+    pub position: Option<usize>,
+    /// Jobs expected to start before this one.
+    pub ahead: usize,
+    /// Jobs running on the slots this one waits for.
+    pub running: usize,
+    /// `max_renders`, or the owner's `max_concurrent` if the server sets no cap.
+    pub slots: usize,
+    /// Expected seconds until the job starts.
+    pub starts_in: u64,
+    /// Expected seconds until the job finishes.
+    pub finishes_in: u64,
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use serde::Serialize;
+use tokio::fs;
+
+use super::Nbt;
+
+/// What a world's `level.dat` says about it.
+#[derive(Serialize, Clone, Debug)]
+pub struct LevelInfo {
+    pub name: Option<String>,
+    /// Minecraft version name, e.g. `1.21.1`.
+    pub version: Option<String>,
+    pub data_version: Option<i64>,
+    /// Only shown to the world's owner.
+    pub seed: Option<i64>,
+    pub spawn: Option<[i64; 3]>,
+    pub game_mode: Option<String>,
+    pub hardcore: bool,
+    /// In milliseconds since epoch.
+    pub last_played: Option<i64>,
+}
+
+impl LevelInfo {
+    pub fn from_nbt(level: &Nbt) -> Option<Self> {
+        let data = level.get("Data")?;
+        let int = |keys: &[&str]| data.path(keys).and_then(Nbt::as_i64);
+
+        let spawn = match (int(&["SpawnX"]), int(&["SpawnY"]), int(&["SpawnZ"])) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            // moved into a compound in 1.21.5
+            _ => match data.path(&["spawn", "pos"]) {
+                Some(Nbt::IntArray(pos)) if pos.len() == 3 => {
+                    Some([pos[0] as i64, pos[1] as i64, pos[2] as i64])
+                }
+                _ => None,
+            },
+        };
+
+        Some(Self {
+            name: data
+                .get("LevelName")
+                .and_then(Nbt::as_str)
+                .map(str::to_string),
+            version: data
+                .path(&["Version", "Name"])
+                .and_then(Nbt::as_str)
+                .map(str::to_string),
+            data_version: int(&["DataVersion"]),
+            // moved into WorldGenSettings in 1.16
+            seed: int(&["WorldGenSettings", "seed"]).or_else(|| int(&["RandomSeed"])),
+            spawn,
+            game_mode: int(&["GameType"]).map(|mode| {
+                match mode {
+                    0 => "survival",
+                    1 => "creative",
+                    2 => "adventure",
+                    3 => "spectator",
+                    _ => "unknown",
+                }
+                .to_string()
+            }),
+            hardcore: int(&["hardcore"]).is_some_and(|hardcore| hardcore != 0),
+            last_played: int(&["LastPlayed"]),
+        })
+    }
+
+    /// Reads `level.dat` from a world folder.
+    pub async fn load(world: &Path) -> Result<Self, String> {
+        let bytes = fs::read(world.join("level.dat"))
+            .await
+            .map_err(|e| e.to_string())?;
+        let level = Nbt::from_bytes(&bytes).map_err(|e| e.to_string())?;
+        Self::from_nbt(&level).ok_or_else(|| "no Data compound".to_string())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WorldDimension {
+    pub dimension: String,
+    pub regions: u64,
+}
+
+/// A world folder as reported by `/worldinfo`.
+#[derive(Serialize, Clone, Debug)]
+pub struct WorldInfo {
+    pub level: LevelInfo,
+    pub dimensions: Vec<WorldDimension>,
+}
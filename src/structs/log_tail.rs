@@ -0,0 +1,136 @@
+use std::{io::SeekFrom, path::PathBuf};
+
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+/// Bytes read from the file at once, the rest is left for the next read.
+const READ_LIMIT: u64 = 64 * 1024;
+
+/// Follows a log file another process appends to, from where it ended when the tail was made.
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    /// Start of a line not fully written yet.
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    pub async fn new(path: PathBuf) -> Self {
+        let offset = fs::metadata(&path)
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+
+        Self {
+            path,
+            offset,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Reads what was written since the last read, false if nothing was.
+    ///
+    /// A file that got shorter was started over and is read again from its start.
+    async fn read(&mut self) -> bool {
+        let Ok(mut file) = File::open(&self.path).await else {
+            return false;
+        };
+        let len = file
+            .metadata()
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if file.seek(SeekFrom::Start(self.offset)).await.is_err() {
+            return false;
+        }
+
+        let mut read = Vec::new();
+        match file.take(READ_LIMIT).read_to_end(&mut read).await {
+            Ok(0) | Err(_) => false,
+            Ok(read_len) => {
+                self.offset += read_len as u64;
+                self.partial.extend_from_slice(&read);
+                true
+            }
+        }
+    }
+
+    /// Complete lines written since the last call, empty lines left out.
+    pub async fn lines(&mut self) -> Vec<String> {
+        self.read().await;
+
+        // a line longer than a read would never end otherwise
+        let end = match self.partial.iter().rposition(|b| *b == b'\n') {
+            Some(end) => end + 1,
+            None if self.partial.len() as u64 >= READ_LIMIT => self.partial.len(),
+            None => return Vec::new(),
+        };
+        let complete = self.partial.drain(..end).collect::<Vec<_>>();
+
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Every line left, the last one even if it does not end in a new line, once the writer is
+    /// done.
+    pub async fn finish(mut self) -> Vec<String> {
+        while self.read().await {}
+
+        let mut lines = self.lines().await;
+        let rest = String::from_utf8_lossy(&self.partial)
+            .trim_end()
+            .to_string();
+        if !rest.is_empty() {
+            lines.push(rest);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn follows_appended_lines() {
+        let path = std::env::temp_dir().join(format!("gmblue-log-{}", fastrand::u64(..)));
+        let append = |content: &str| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .write_all(content.as_bytes())
+                .unwrap()
+        };
+
+        append("before the render\n");
+        let mut tail = LogTail::new(path.clone()).await;
+        assert!(tail.lines().await.is_empty());
+
+        append("[INFO] Loading\n\n[INFO] Rend");
+        assert_eq!(tail.lines().await, ["[INFO] Loading"]);
+        append("ering\n");
+        assert_eq!(tail.lines().await, ["[INFO] Rendering"]);
+
+        // started over by the next render
+        std::fs::write(&path, "[INFO] Start\n").unwrap();
+        assert_eq!(tail.lines().await, ["[INFO] Start"]);
+
+        append("[INFO] Done");
+        assert_eq!(tail.finish().await, ["[INFO] Done"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
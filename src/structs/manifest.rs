@@ -0,0 +1,153 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::functions::{format_duration, format_time};
+
+use super::{RenderArea, RenderTask};
+
+/// Written into every map output on a successful render.
+pub const MANIFEST_FILE: &str = "gmblue-render.json";
+/// Written into BlueMap outputs imported from elsewhere, marking them as maps.
+pub const IMPORT_FILE: &str = "gmblue-import.json";
+
+/// Describes how a map was rendered.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RenderManifest {
+    /// Source world, relative to the owner's directory.
+    pub from: PathBuf,
+    pub preset: String,
+    /// Id of the account the render ran under.
+    pub user: i64,
+    /// In seconds since epoch.
+    pub started: u64,
+    /// In seconds since epoch.
+    pub finished: u64,
+    /// BlueMap version as reported by the webapp's `settings.json`.
+    pub bluemap: Option<String>,
+    /// Dimensions rendered as separate maps, empty if only the preset's dimension was rendered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dimensions: Vec<String>,
+    #[serde(default, skip_serializing_if = "RenderArea::is_unbounded")]
+    pub area: RenderArea,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, serde_json::Value>,
+    /// Re-renders keep the previous version as a snapshot instead of overwriting it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keep_versions: bool,
+    /// Ties the map to its snapshots wherever it is moved, given the first time it keeps a
+    /// version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub map_id: Option<u64>,
+}
+
+impl RenderManifest {
+    pub async fn load(map: &Path) -> Option<Self> {
+        serde_json::from_slice(&fs::read(map.join(MANIFEST_FILE)).await.ok()?).ok()
+    }
+
+    pub async fn save(&self, map: &Path) -> std::io::Result<()> {
+        fs::write(map.join(MANIFEST_FILE), serde_json::to_vec_pretty(self)?).await
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.finished.saturating_sub(self.started)
+    }
+
+    /// Whether `task` renders the map as it was rendered, so an update only needs to redo the
+    /// regions changed since. Other settings apply to every region.
+    pub fn renders_like(&self, task: &RenderTask) -> bool {
+        self.from == task.from
+            && self.preset == task.preset
+            && self.dimensions == task.dimensions
+            && self.area == task.area
+            && self.overrides == task.overrides
+    }
+
+    /// e.g. "rendered from blue/worlds/survival with overworld.conf on 2026-10-01 in 4m12s"
+    pub fn describe(&self) -> String {
+        let date = chrono::DateTime::from_timestamp(self.finished as i64, 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        let dimensions = if self.dimensions.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.dimensions.join(", "))
+        };
+
+        format!(
+            "rendered from {} with {}{dimensions} on {date} in {}",
+            self.from.to_string_lossy(),
+            self.preset,
+            format_duration(self.duration())
+        )
+    }
+}
+
+/// Describes where an imported map came from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportManifest {
+    /// Uploaded folder or archive, relative to the owner's directory.
+    pub from: PathBuf,
+    pub user: i64,
+    /// In seconds since epoch.
+    pub imported: u64,
+    pub bluemap: Option<String>,
+}
+
+impl ImportManifest {
+    pub async fn load(map: &Path) -> Option<Self> {
+        serde_json::from_slice(&fs::read(map.join(IMPORT_FILE)).await.ok()?).ok()
+    }
+
+    pub async fn save(&self, map: &Path) -> std::io::Result<()> {
+        fs::write(map.join(IMPORT_FILE), serde_json::to_vec_pretty(self)?).await
+    }
+
+    /// e.g. "imported from blue/uploads/web.zip on 2026-10-01"
+    pub fn describe(&self) -> String {
+        let date = chrono::DateTime::from_timestamp(self.imported as i64, 0)
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        format!("imported from {} on {date}", self.from.to_string_lossy())
+    }
+}
+
+/// A previous version of a map, kept when the map was rendered again.
+#[derive(Serialize, Clone, Debug)]
+pub struct Snapshot {
+    /// When the version finished rendering, in seconds since epoch.
+    pub id: u64,
+    pub manifest: Option<RenderManifest>,
+}
+
+impl Snapshot {
+    /// e.g. "2026-10-01 04:12"
+    pub fn date(&self) -> String {
+        format_time(self.id)
+    }
+}
+
+/// Reads the BlueMap version from a rendered map.
+pub async fn bluemap_version(map: &Path) -> Option<String> {
+    for settings in [
+        map.join("settings.json"),
+        map.join("web").join("settings.json"),
+    ] {
+        if let Ok(content) = fs::read(&settings).await {
+            return serde_json::from_slice::<serde_json::Value>(&content)
+                .ok()?
+                .get("version")?
+                .as_str()
+                .map(str::to_string);
+        }
+    }
+
+    None
+}
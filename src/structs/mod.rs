@@ -2,3 +2,33 @@ mod config;
 pub use config::*;
 mod tasks;
 pub use tasks::*;
+mod renders;
+pub use renders::*;
+mod slots;
+pub use slots::*;
+mod v1;
+pub use v1::*;
+mod manifest;
+pub use manifest::*;
+mod preset;
+pub use preset::*;
+mod reloadable;
+pub use reloadable::*;
+mod area;
+pub use area::*;
+mod nbt;
+pub use nbt::*;
+mod level;
+pub use level::*;
+mod estimate;
+pub use estimate::*;
+mod staging;
+pub use staging::*;
+mod watch;
+pub use watch::*;
+mod cron;
+pub use cron::*;
+mod schedule;
+pub use schedule::*;
+mod log_tail;
+pub use log_tail::*;
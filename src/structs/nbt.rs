@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+/// Compounds and lists nested deeper than this are rejected, `level.dat` only nests a few levels.
+const MAX_DEPTH: usize = 64;
+/// Elements a single list or array may have.
+const MAX_LEN: usize = 1 << 20;
+/// Tags a file may have in total, so small tags cannot add up to far more memory than the file.
+const MAX_TAGS: usize = 1 << 21;
+/// Decompressed size limit, `level.dat` is usually a few KiB.
+const MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// A tag of Minecraft's Named Binary Tag format, as used by `level.dat`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Nbt {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Nbt>),
+    Compound(HashMap<String, Nbt>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Nbt {
+    /// Reads a file's root tag, gzip (as `level.dat` is), zlib or uncompressed.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut data = Vec::new();
+        match bytes {
+            [0x1f, 0x8b, ..] => {
+                GzDecoder::new(bytes)
+                    .take(MAX_SIZE)
+                    .read_to_end(&mut data)?;
+            }
+            [0x78, ..] => {
+                ZlibDecoder::new(bytes)
+                    .take(MAX_SIZE)
+                    .read_to_end(&mut data)?;
+            }
+            _ => data.extend_from_slice(bytes),
+        }
+
+        let mut reader = Reader {
+            data: &data,
+            tags: 0,
+        };
+        let id = reader.u8()?;
+        if id != 10 {
+            return Err(invalid("root tag is not a compound"));
+        }
+        reader.string()?;
+        reader.payload(id, 0)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Nbt> {
+        match self {
+            Self::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Follows compound keys, `["Data", "Version", "Name"]`.
+    pub fn path(&self, keys: &[&str]) -> Option<&Nbt> {
+        keys.iter().try_fold(self, |tag, key| tag.get(key))
+    }
+
+    /// Any integer tag, widened.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Byte(v) => Some(*v as i64),
+            Self::Short(v) => Some(*v as i64),
+            Self::Int(v) => Some(*v as i64),
+            Self::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    /// Tags read so far.
+    tags: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(invalid("unexpected end of data"));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    /// Length prefix of an array or list, checked against what is left so a corrupt file cannot
+    /// make us allocate gigabytes.
+    fn len(&mut self, element_size: usize) -> io::Result<usize> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Err(invalid("negative length"));
+        }
+        let len = len as usize;
+        if len > MAX_LEN {
+            return Err(invalid("list or array too long"));
+        }
+        if len.saturating_mul(element_size) > self.data.len() {
+            return Err(invalid("length runs past the end of data"));
+        }
+        Ok(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        // modified UTF-8 only differs from UTF-8 for nulls and supplementary characters
+        Ok(String::from_utf8_lossy(self.take(len)?).to_string())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> io::Result<Nbt> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("tags nested too deep"));
+        }
+        self.tags += 1;
+        if self.tags > MAX_TAGS {
+            return Err(invalid("too many tags"));
+        }
+
+        Ok(match id {
+            1 => Nbt::Byte(self.u8()? as i8),
+            2 => Nbt::Short(i16::from_be_bytes(self.array()?)),
+            3 => Nbt::Int(self.i32()?),
+            4 => Nbt::Long(i64::from_be_bytes(self.array()?)),
+            5 => Nbt::Float(f32::from_be_bytes(self.array()?)),
+            6 => Nbt::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.len(1)?;
+                Nbt::ByteArray(self.take(len)?.iter().map(|b| *b as i8).collect())
+            }
+            8 => Nbt::String(self.string()?),
+            9 => {
+                let element = self.u8()?;
+                let len = self.len(if element == 0 { 0 } else { 1 })?;
+                let mut list = Vec::with_capacity(len.min(self.data.len()));
+                for _ in 0..len {
+                    list.push(self.payload(element, depth + 1)?);
+                }
+                Nbt::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let id = self.u8()?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(id, depth + 1)?);
+                }
+                Nbt::Compound(map)
+            }
+            11 => {
+                let len = self.len(4)?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    array.push(self.i32()?);
+                }
+                Nbt::IntArray(array)
+            }
+            12 => {
+                let len = self.len(8)?;
+                let mut array = Vec::with_capacity(len);
+                for _ in 0..len {
+                    array.push(i64::from_be_bytes(self.array()?));
+                }
+                Nbt::LongArray(array)
+            }
+            other => return Err(invalid(&format!("unknown tag type {other}"))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    /// A named tag's header.
+    fn named(id: u8, name: &str) -> Vec<u8> {
+        let mut bytes = vec![id];
+        bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes
+    }
+
+    /// A root compound holding `tags`.
+    fn root(tags: &[u8]) -> Vec<u8> {
+        let mut bytes = named(10, "");
+        bytes.extend_from_slice(tags);
+        bytes.push(0);
+        bytes
+    }
+
+    fn level() -> Vec<u8> {
+        let mut tags = named(10, "Data");
+        tags.extend(named(8, "LevelName"));
+        tags.extend_from_slice(&5u16.to_be_bytes());
+        tags.extend_from_slice(b"world");
+        tags.extend(named(3, "DataVersion"));
+        tags.extend_from_slice(&3955i32.to_be_bytes());
+        tags.extend(named(9, "Enabled"));
+        tags.push(8);
+        tags.extend_from_slice(&1i32.to_be_bytes());
+        tags.extend_from_slice(&7u16.to_be_bytes());
+        tags.extend_from_slice(b"vanilla");
+        tags.push(0);
+        root(&tags)
+    }
+
+    #[test]
+    fn reads_level() {
+        let nbt = Nbt::from_bytes(&level()).unwrap();
+        assert_eq!(
+            nbt.path(&["Data", "LevelName"]).and_then(Nbt::as_str),
+            Some("world")
+        );
+        assert_eq!(
+            nbt.path(&["Data", "DataVersion"]).and_then(Nbt::as_i64),
+            Some(3955)
+        );
+        assert_eq!(
+            nbt.path(&["Data", "Enabled"]),
+            Some(&Nbt::List(vec![Nbt::String("vanilla".to_string())]))
+        );
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&level()).unwrap();
+        assert_eq!(Nbt::from_bytes(&gzip.finish().unwrap()).unwrap(), nbt);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = level();
+        for len in 0..bytes.len() {
+            assert!(Nbt::from_bytes(&bytes[..len]).is_err(), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn negative_lengths() {
+        for id in [7, 11, 12] {
+            let mut tags = named(id, "array");
+            tags.extend_from_slice(&(-1i32).to_be_bytes());
+            assert!(Nbt::from_bytes(&root(&tags)).is_err(), "tag type {id}");
+        }
+
+        let mut tags = named(9, "list");
+        tags.push(1);
+        tags.extend_from_slice(&i32::MIN.to_be_bytes());
+        assert!(Nbt::from_bytes(&root(&tags)).is_err());
+    }
+
+    #[test]
+    fn lengths_past_the_end() {
+        let mut tags = named(12, "array");
+        tags.extend_from_slice(&2i32.to_be_bytes());
+        tags.extend_from_slice(&1i64.to_be_bytes());
+        assert!(Nbt::from_bytes(&root(&tags)).is_err());
+    }
+
+    #[test]
+    fn long_lists() {
+        let list = |len: usize| {
+            let mut tags = named(9, "list");
+            tags.push(1);
+            tags.extend_from_slice(&(len as i32).to_be_bytes());
+            tags.resize(tags.len() + len, 0);
+            root(&tags)
+        };
+        assert!(Nbt::from_bytes(&list(MAX_LEN)).is_ok());
+        assert!(Nbt::from_bytes(&list(MAX_LEN + 1)).is_err());
+
+        // lists of lists, each short enough but too many tags together
+        let mut tags = named(9, "lists");
+        tags.push(9);
+        tags.extend_from_slice(&3i32.to_be_bytes());
+        for _ in 0..3 {
+            tags.push(1);
+            tags.extend_from_slice(&(MAX_LEN as i32).to_be_bytes());
+            tags.resize(tags.len() + MAX_LEN, 0);
+        }
+        assert!(Nbt::from_bytes(&root(&tags)).is_err());
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // compounds inside the root, each one level deeper
+        let nested = |depth: usize| {
+            let mut tags = Vec::new();
+            for _ in 0..depth {
+                tags.extend(named(10, "a"));
+            }
+            tags.resize(tags.len() + depth, 0);
+            root(&tags)
+        };
+        assert!(Nbt::from_bytes(&nested(MAX_DEPTH)).is_ok());
+        assert!(Nbt::from_bytes(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(Nbt::from_bytes(&nested(100_000)).is_err());
+
+        // lists of one list each
+        let mut tags = named(9, "a");
+        for _ in 0..100_000 {
+            tags.push(9);
+            tags.extend_from_slice(&1i32.to_be_bytes());
+        }
+        assert!(Nbt::from_bytes(&root(&tags)).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::functions::conf_value;
+
+/// Image extensions looked for next to a preset, in order.
+pub const PREVIEW_EXTENSIONS: [&str; 4] = ["webp", "png", "jpg", "jpeg"];
+
+/// Optional `<preset>.json` next to a preset file.
+#[derive(Deserialize, Default)]
+pub struct PresetSidecar {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PresetInfo {
+    /// As passed to the render endpoint.
+    pub preset: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub dimension: Option<String>,
+    pub ambient_light: Option<f64>,
+    pub hires: Option<bool>,
+    pub render_edges: Option<bool>,
+    pub preview: Option<String>,
+}
+
+impl PresetInfo {
+    /// Metadata from the preset's own settings, overridden by the sidecar where given.
+    ///
+    /// Without a sidecar description, the comment lines at the top of the preset are used.
+    pub fn new(
+        preset: String,
+        conf: &str,
+        sidecar: PresetSidecar,
+        preview: Option<String>,
+    ) -> Self {
+        let header = conf
+            .lines()
+            .map(str::trim)
+            .take_while(|line| line.starts_with('#') || line.starts_with("//"))
+            .map(|line| line.trim_start_matches(['#', '/']).trim())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self {
+            name: sidecar
+                .name
+                .or_else(|| conf_value(conf, "name"))
+                .unwrap_or_else(|| {
+                    Path::new(&preset)
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                }),
+            description: sidecar
+                .description
+                .or_else(|| Some(header).filter(|header| !header.is_empty())),
+            dimension: conf_value(conf, "dimension"),
+            ambient_light: conf_value(conf, "ambient-light").and_then(|v| v.parse().ok()),
+            hires: conf_value(conf, "save-hires-layer").and_then(|v| v.parse().ok()),
+            render_edges: conf_value(conf, "render-edges").and_then(|v| v.parse().ok()),
+            preview,
+            preset,
+        }
+    }
+}
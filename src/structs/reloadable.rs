@@ -0,0 +1,31 @@
+use std::sync::{Arc, RwLock};
+
+/// A value set in `values::init` that can be swapped out at runtime.
+///
+/// Readers hold on to the `Arc` they got, so a reload never changes a value halfway through a
+/// request or a render.
+pub struct Reloadable<T>(RwLock<Option<Arc<T>>>);
+
+impl<T> Reloadable<T> {
+    pub const fn new() -> Self {
+        Self(RwLock::new(None))
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.0
+            .read()
+            .unwrap()
+            .clone()
+            .expect("value read before values::init")
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap() = Some(Arc::new(value));
+    }
+}
+
+impl<T> Default for Reloadable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
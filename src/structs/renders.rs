@@ -0,0 +1,491 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use goodmorning_services::{
+    bindings::services::v1::V1Response,
+    structs::{Account, Jobs},
+    traits::TaskItem,
+};
+use mongodb::{bson::doc, Collection};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch, Notify};
+
+use crate::{
+    functions::{now, queue_limits},
+    values::VALUES,
+};
+
+use super::{ExportTask, ImportTask, RenderSlots, RenderTask, V1BlueError, V1BlueResponse};
+
+/// How long a finished job stays around for status queries.
+pub const FINISHED_RETENTION: u64 = 3600;
+/// Log lines kept per job, older lines are dropped first.
+const LOG_LIMIT: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum RenderStatus {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "succeeded")]
+    Succeeded { result: serde_json::Value },
+    #[serde(rename = "failed")]
+    Failed { error: serde_json::Value },
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl RenderStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Self::Succeeded { .. } | Self::Failed { .. } | Self::Cancelled
+        )
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderPhase {
+    #[default]
+    Queued,
+    Scanning,
+    Rendering,
+    /// Writing an export's zip.
+    Archiving,
+    Finishing,
+    Done,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct RenderProgress {
+    pub phase: RenderPhase,
+    /// Hires tiles written so far.
+    pub done: u64,
+    /// Estimated hires tiles in total, 0 until the source has been scanned.
+    pub total: u64,
+    pub started: Option<u64>,
+    pub log: Vec<String>,
+}
+
+/// A task tracked by `RenderJobs`.
+pub trait JobTask: Clone + Into<BlueTask> {
+    fn owner(&self) -> i64;
+    fn job(&self) -> u64;
+    fn set_job(&mut self, id: u64);
+
+    /// What a succeeded job's status holds, the `V1Response` its run returned if `None`.
+    fn result(&self) -> Option<V1BlueResponse> {
+        None
+    }
+}
+
+/// Every kind of task `RenderJobs` tracks.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum BlueTask {
+    #[serde(rename = "render")]
+    Render(RenderTask),
+    #[serde(rename = "export")]
+    Export(ExportTask),
+    #[serde(rename = "import")]
+    Import(ImportTask),
+}
+
+impl From<RenderTask> for BlueTask {
+    fn from(task: RenderTask) -> Self {
+        Self::Render(task)
+    }
+}
+
+impl From<ExportTask> for BlueTask {
+    fn from(task: ExportTask) -> Self {
+        Self::Export(task)
+    }
+}
+
+impl From<ImportTask> for BlueTask {
+    fn from(task: ImportTask) -> Self {
+        Self::Import(task)
+    }
+}
+
+/// A job as stored in MongoDB, so it survives a restart.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobRecord {
+    pub id: u64,
+    pub owner: i64,
+    pub task: BlueTask,
+    pub created: u64,
+    pub finished: Option<u64>,
+    pub status: RenderStatus,
+    /// Times the job was queued again after a restart interrupted it.
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+pub struct RenderJob {
+    pub owner: i64,
+    pub task: BlueTask,
+    pub created: u64,
+    pub finished: Option<u64>,
+    pub progress: RenderProgress,
+    pub cancelled: bool,
+    pub attempts: u32,
+    /// Regions the job renders, once known, for queue estimates.
+    pub regions: Option<u64>,
+    cancel: Arc<Notify>,
+    status: watch::Sender<RenderStatus>,
+}
+
+impl RenderJob {
+    pub fn status(&self) -> RenderStatus {
+        self.status.borrow().clone()
+    }
+
+    fn record(&self, id: u64) -> JobRecord {
+        JobRecord {
+            id,
+            owner: self.owner,
+            task: self.task.clone(),
+            created: self.created,
+            finished: self.finished,
+            status: self.status(),
+            attempts: self.attempts,
+        }
+    }
+}
+
+/// What queue estimates need to know about a job.
+pub struct QueuedJob {
+    pub id: u64,
+    pub owner: i64,
+    pub task: BlueTask,
+    pub regions: Option<u64>,
+    pub progress: RenderProgress,
+}
+
+impl QueuedJob {
+    fn new(id: u64, job: &RenderJob) -> Self {
+        Self {
+            id,
+            owner: job.owner,
+            task: job.task.clone(),
+            regions: job.regions,
+            progress: job.progress.clone(),
+        }
+    }
+}
+
+/// A change to the stored job records.
+enum StoreOp {
+    Save(Box<JobRecord>),
+    Remove(u64),
+}
+
+/// Every render or export submitted to `Jobs`, keyed by its job id.
+#[derive(Default)]
+pub struct RenderJobs {
+    jobs: Mutex<HashMap<u64, RenderJob>>,
+    /// Writes job records in the order they changed, once `store_in` has been called.
+    store: OnceLock<mpsc::UnboundedSender<StoreOp>>,
+    /// Shared by every account, so the server never runs more than `max_renders` jobs.
+    pub slots: RenderSlots,
+}
+
+impl RenderJobs {
+    /// Keeps a record of every job in `collection` from now on.
+    pub fn store_in(&self, collection: Collection<JobRecord>) {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<StoreOp>();
+        if self.store.set(sender).is_err() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            while let Some(op) = receiver.recv().await {
+                match op {
+                    StoreOp::Save(record) => {
+                        if let Err(e) = collection
+                            .replace_one(doc! { "id": record.id as i64 }, &*record)
+                            .upsert(true)
+                            .await
+                        {
+                            log::warn!("failed to store job {}: {e}", record.id);
+                        }
+                    }
+                    StoreOp::Remove(id) => {
+                        if let Err(e) = collection.delete_one(doc! { "id": id as i64 }).await {
+                            log::warn!("failed to remove stored job {id}: {e}");
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Called with the lock held, so records are sent in the order the jobs changed.
+    fn persist(&self, id: u64, job: &RenderJob) {
+        if let Some(store) = self.store.get() {
+            let _ = store.send(StoreOp::Save(Box::new(job.record(id))));
+        }
+    }
+
+    /// Removes the record of a job no longer tracked, called with the lock held like `persist`.
+    fn unpersist(&self, id: u64) {
+        if let Some(store) = self.store.get() {
+            let _ = store.send(StoreOp::Remove(id));
+        }
+    }
+
+    /// Tracks a job loaded from its record, under the id it had before.
+    pub fn restore(&self, record: JobRecord) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = RenderJob {
+            owner: record.owner,
+            task: record.task,
+            created: record.created,
+            finished: record.finished,
+            progress: RenderProgress {
+                phase: if record.status.is_finished() {
+                    RenderPhase::Done
+                } else {
+                    RenderPhase::Queued
+                },
+                ..Default::default()
+            },
+            cancelled: matches!(record.status, RenderStatus::Cancelled),
+            attempts: record.attempts,
+            regions: None,
+            cancel: Arc::new(Notify::new()),
+            status: watch::Sender::new(record.status),
+        };
+        self.persist(record.id, &job);
+        jobs.insert(record.id, job);
+    }
+
+    /// Assigns a job id to the task and tracks it as queued.
+    pub fn register<T: JobTask>(&self, mut task: T) -> T {
+        let now = now();
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|id, job| {
+            let keep = job
+                .finished
+                .is_none_or(|finished| now < finished + FINISHED_RETENTION);
+            if !keep {
+                self.unpersist(*id);
+            }
+            keep
+        });
+
+        let mut id = fastrand::u64(..1 << 53);
+        while jobs.contains_key(&id) {
+            id = fastrand::u64(..1 << 53);
+        }
+
+        task.set_job(id);
+        let job = RenderJob {
+            owner: task.owner(),
+            task: task.clone().into(),
+            created: now,
+            finished: None,
+            progress: RenderProgress::default(),
+            cancelled: false,
+            attempts: 0,
+            regions: None,
+            cancel: Arc::new(Notify::new()),
+            status: watch::Sender::new(RenderStatus::Queued),
+        };
+        self.persist(id, &job);
+        jobs.insert(id, job);
+        task
+    }
+
+    pub fn set_status(&self, id: u64, status: RenderStatus) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            if status.is_finished() {
+                job.finished = Some(now());
+                job.progress.phase = RenderPhase::Done;
+            }
+            job.status.send_replace(status);
+            self.persist(id, job);
+        }
+    }
+
+    /// Status and progress of a job, only if it belongs to `owner`.
+    pub fn status(&self, id: u64, owner: i64) -> Option<(RenderStatus, RenderProgress)> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .filter(|job| job.owner == owner)
+            .map(|job| (job.status(), job.progress.clone()))
+    }
+
+    /// Cancels a job that has not finished yet, only if it belongs to `owner`.
+    ///
+    /// A queued job is marked cancelled straight away and skipped once `Jobs` gets to it, a running
+    /// one is stopped by `RenderTask::run`.
+    pub fn cancel(&self, id: u64, owner: i64) -> Result<(), V1BlueError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = match jobs.get_mut(&id) {
+            Some(job) if job.owner == owner => job,
+            _ => return Err(V1BlueError::JobNotFound),
+        };
+
+        if job.status().is_finished() {
+            return Err(V1BlueError::JobFinished);
+        }
+
+        job.cancelled = true;
+        job.cancel.notify_one();
+        if matches!(job.status(), RenderStatus::Queued) {
+            job.finished = Some(now());
+            job.progress.phase = RenderPhase::Done;
+            job.status.send_replace(RenderStatus::Cancelled);
+            self.persist(id, job);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_cancelled(&self, id: u64) -> bool {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|job| job.cancelled)
+    }
+
+    /// Resolves once the job is cancelled.
+    pub async fn cancelled(&self, id: u64) {
+        let cancel = self
+            .jobs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|job| job.cancel.clone());
+
+        match cancel {
+            Some(cancel) => cancel.notified().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    pub fn set_regions(&self, id: u64, regions: u64) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            job.regions = Some(regions);
+        }
+    }
+
+    /// A job as the queue estimate sees it, regardless of its owner.
+    pub fn queued(&self, id: u64) -> Option<QueuedJob> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|job| QueuedJob::new(id, job))
+    }
+
+    /// Every job that has started and not finished yet.
+    pub fn running(&self) -> Vec<QueuedJob> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, job)| matches!(job.status(), RenderStatus::Running))
+            .map(|(id, job)| QueuedJob::new(*id, job))
+            .collect()
+    }
+
+    pub fn update_progress(&self, id: u64, f: impl FnOnce(&mut RenderProgress)) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            f(&mut job.progress)
+        }
+    }
+
+    pub fn log(&self, id: u64, line: impl Into<String>) {
+        self.update_progress(id, |progress| {
+            if progress.log.len() >= LOG_LIMIT {
+                progress.log.remove(0);
+            }
+            progress.log.push(line.into());
+        })
+    }
+
+    /// Hands a registered task to `Jobs` under the account's queue limits and records the outcome.
+    pub async fn run<T: JobTask + TaskItem + 'static>(
+        &self,
+        jobs: &Jobs,
+        account: &Account,
+        task: T,
+    ) -> Result<V1Response, Box<dyn Error>> {
+        let id = task.job();
+        let (max_concurrent, queue_limit) = queue_limits(&account.limit);
+
+        let weight = VALUES.get().config.tier_weight(&account.limit);
+        let slot = tokio::select! {
+            slot = self.slots.acquire(id, account.id, weight, max_concurrent, queue_limit) => slot,
+            _ = self.cancelled(id) => return Err("job cancelled".into()),
+        };
+        let _slot = match slot {
+            Ok(slot) => slot,
+            Err(e) => {
+                self.set_status(
+                    id,
+                    RenderStatus::Failed {
+                        error: serde_json::to_value(&e)?,
+                    },
+                );
+                return Err(e.into());
+            }
+        };
+
+        let result = task.result();
+        let res = jobs
+            .run_with_limit(
+                account.id,
+                Box::new(task),
+                max_concurrent,
+                queue_limit,
+                goodmorning_services::bindings::structs::ApiVer::V1,
+                Duration::from_secs(VALUES.get().config.render_timeout),
+            )
+            .await
+            .as_v1();
+
+        if self.is_cancelled(id) {
+            self.set_status(id, RenderStatus::Cancelled);
+            return res.map_err(Into::into);
+        }
+
+        match res {
+            Ok(res) => {
+                self.set_status(
+                    id,
+                    RenderStatus::Succeeded {
+                        result: match result {
+                            Some(result) => serde_json::to_value(&result)?,
+                            None => serde_json::to_value(&res)?,
+                        },
+                    },
+                );
+                Ok(res)
+            }
+            Err(e) => {
+                self.set_status(
+                    id,
+                    RenderStatus::Failed {
+                        error: serde_json::to_value(&e)?,
+                    },
+                );
+                Err(e.into())
+            }
+        }
+    }
+}
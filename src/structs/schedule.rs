@@ -0,0 +1,127 @@
+use std::{error::Error, path::PathBuf};
+
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::values::SCHEDULES;
+
+use super::{Cron, RenderStatus};
+
+/// Schedules each account may have.
+pub const SCHEDULE_LIMIT: usize = 20;
+
+/// How the last scheduled render went.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduleRun {
+    pub job: u64,
+    /// In seconds since epoch.
+    pub started: u64,
+    pub finished: Option<u64>,
+    pub status: RenderStatus,
+}
+
+/// A render run at the times of a cron expression, updating the target once it exists.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RenderSchedule {
+    pub id: u64,
+    pub user: i64,
+    /// Relative to the owner's directory.
+    pub from: PathBuf,
+    /// Relative to the owner's directory.
+    pub to: PathBuf,
+    pub preset: String,
+    /// As given, parsed with `Cron::parse`.
+    pub schedule: String,
+    pub paused: bool,
+    /// In seconds since epoch, `None` if the expression never matches again.
+    pub next_run: Option<u64>,
+    pub last_run: Option<ScheduleRun>,
+}
+
+impl RenderSchedule {
+    pub fn cron(&self) -> Result<Cron, String> {
+        Cron::parse(&self.schedule)
+    }
+
+    pub async fn of_user(user: i64) -> Result<Vec<Self>, Box<dyn Error>> {
+        let mut schedules = Vec::new();
+        let mut cursor = SCHEDULES
+            .get()
+            .unwrap()
+            .find(doc! { "user": user })
+            .sort(doc! { "id": 1 })
+            .await?;
+        while let Some(schedule) = cursor.next().await {
+            schedules.push(schedule?);
+        }
+        Ok(schedules)
+    }
+
+    /// Unpaused schedules due at `now`.
+    pub async fn due(now: u64) -> Result<Vec<Self>, Box<dyn Error>> {
+        let mut schedules = Vec::new();
+        let mut cursor = SCHEDULES
+            .get()
+            .unwrap()
+            .find(doc! { "paused": false, "next_run": { "$lte": now as i64 } })
+            .await?;
+        while let Some(schedule) = cursor.next().await {
+            schedules.push(schedule?);
+        }
+        Ok(schedules)
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn Error>> {
+        SCHEDULES
+            .get()
+            .unwrap()
+            .replace_one(doc! { "id": self.id as i64 }, self)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    /// Records when the schedule runs next, and the run just started if any, without touching
+    /// fields the owner may have edited meanwhile.
+    pub async fn set_runs(
+        id: u64,
+        next_run: Option<u64>,
+        last_run: Option<&ScheduleRun>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut set = doc! { "next_run": next_run.map(|next| next as i64) };
+        if let Some(run) = last_run {
+            set.insert("last_run", mongodb::bson::to_bson(run)?);
+        }
+
+        SCHEDULES
+            .get()
+            .unwrap()
+            .update_one(doc! { "id": id as i64 }, doc! { "$set": set })
+            .await?;
+        Ok(())
+    }
+
+    /// Records how a run went, without touching fields the owner may have edited meanwhile.
+    pub async fn set_last_run(id: u64, run: &ScheduleRun) -> Result<(), Box<dyn Error>> {
+        SCHEDULES
+            .get()
+            .unwrap()
+            .update_one(
+                doc! { "id": id as i64 },
+                doc! { "$set": { "last_run": mongodb::bson::to_bson(run)? } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(user: i64, id: u64) -> Result<bool, Box<dyn Error>> {
+        Ok(SCHEDULES
+            .get()
+            .unwrap()
+            .delete_one(doc! { "user": user, "id": id as i64 })
+            .await?
+            .deleted_count
+            > 0)
+    }
+}
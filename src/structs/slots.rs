@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::oneshot;
+
+use crate::values::VALUES;
+
+use super::V1BlueError;
+
+/// Server-wide cap on jobs running at once, `max_renders` in the blue config.
+///
+/// Free slots go to the waiting account with the fewest running jobs for its tier's weight, ties
+/// going to whoever was served least recently, so one account queueing many jobs cannot hold up
+/// everyone else. An account's own jobs start in the order they were queued.
+#[derive(Default)]
+pub struct RenderSlots {
+    state: Arc<Mutex<SlotsState>>,
+}
+
+#[derive(Default)]
+struct SlotsState {
+    /// Jobs holding a slot, by owner.
+    running: HashMap<i64, usize>,
+    /// Jobs waiting for a slot, in the order they asked for one.
+    waiting: Vec<Waiter>,
+    /// When each owner was last given a slot, counted in slots given out.
+    last_served: HashMap<i64, u64>,
+    served: u64,
+}
+
+struct Waiter {
+    job: u64,
+    owner: i64,
+    weight: u32,
+    max_concurrent: usize,
+    ready: oneshot::Sender<RenderSlot>,
+}
+
+/// A slot held by a running job, given back when dropped.
+pub struct RenderSlot {
+    state: Arc<Mutex<SlotsState>>,
+    owner: i64,
+    held: bool,
+}
+
+impl Drop for RenderSlot {
+    fn drop(&mut self) {
+        if !self.held {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.release(self.owner);
+        state.dispatch(&self.state);
+    }
+}
+
+impl SlotsState {
+    fn release(&mut self, owner: i64) {
+        if let Some(running) = self.running.get_mut(&owner) {
+            *running -= 1;
+            if *running == 0 {
+                self.running.remove(&owner);
+            }
+        }
+    }
+
+    /// Index of the waiter to start next, if any may start.
+    fn next(&self) -> Option<usize> {
+        pick(
+            &self.running,
+            &self.last_served,
+            &self.waiting.iter().collect::<Vec<_>>(),
+            true,
+        )
+    }
+
+    /// Hands out free slots until none are left or nobody waiting may start.
+    fn dispatch(&mut self, shared: &Arc<Mutex<SlotsState>>) {
+        // waiters whose job was cancelled or stopped
+        self.waiting.retain(|waiter| !waiter.ready.is_closed());
+
+        while VALUES
+            .get()
+            .config
+            .max_renders
+            .is_none_or(|max| self.running.values().sum::<usize>() < max)
+        {
+            let Some(index) = self.next() else {
+                break;
+            };
+            let waiter = self.waiting.remove(index);
+
+            *self.running.entry(waiter.owner).or_default() += 1;
+            self.served += 1;
+            self.last_served.insert(waiter.owner, self.served);
+
+            let slot = RenderSlot {
+                state: shared.clone(),
+                owner: waiter.owner,
+                held: true,
+            };
+            if let Err(mut slot) = waiter.ready.send(slot) {
+                // the lock is already held, give the slot back here instead of in drop
+                slot.held = false;
+                self.release(waiter.owner);
+            }
+        }
+    }
+}
+
+/// Index in `waiting` of the job to start next, only among owners below their `max_concurrent` if
+/// `capped`.
+fn pick(
+    running: &HashMap<i64, usize>,
+    last_served: &HashMap<i64, u64>,
+    waiting: &[&Waiter],
+    capped: bool,
+) -> Option<usize> {
+    let running = |owner| running.get(&owner).copied().unwrap_or_default();
+
+    waiting
+        .iter()
+        .enumerate()
+        .filter(|(_, waiter)| !capped || running(waiter.owner) < waiter.max_concurrent)
+        .min_by(|(a_index, a), (b_index, b)| {
+            // fewest running jobs for the weight, compared without dividing
+            (running(a.owner) as u64 * b.weight as u64)
+                .cmp(&(running(b.owner) as u64 * a.weight as u64))
+                .then_with(|| b.weight.cmp(&a.weight))
+                .then_with(|| {
+                    let last_served = |owner| last_served.get(&owner).copied();
+                    last_served(a.owner).cmp(&last_served(b.owner))
+                })
+                .then(a_index.cmp(b_index))
+        })
+        .map(|(index, _)| index)
+}
+
+impl RenderSlots {
+    /// Waits for a slot for `job`, one of `owner`'s jobs.
+    ///
+    /// Fails straight away if `owner` already has `queue_limit` jobs waiting.
+    pub async fn acquire(
+        &self,
+        job: u64,
+        owner: i64,
+        weight: u32,
+        max_concurrent: usize,
+        queue_limit: usize,
+    ) -> Result<RenderSlot, V1BlueError> {
+        let (ready, slot) = oneshot::channel();
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let waiting = state
+                .waiting
+                .iter()
+                .filter(|waiter| waiter.owner == owner && !waiter.ready.is_closed())
+                .count();
+            if waiting >= queue_limit {
+                return Err(V1BlueError::QueueFull { limit: queue_limit });
+            }
+
+            state.waiting.push(Waiter {
+                job,
+                owner,
+                weight: weight.max(1),
+                max_concurrent: max_concurrent.max(1),
+                ready,
+            });
+            state.dispatch(&self.state);
+        }
+
+        Ok(slot.await.unwrap())
+    }
+
+    /// Gives out slots freed by a raised `max_renders`, called after a config reload.
+    pub fn refresh(&self) {
+        self.state.lock().unwrap().dispatch(&self.state);
+    }
+
+    /// Waiting jobs with their owners, in the order they are expected to start.
+    ///
+    /// Assumes every account may start as many jobs as it has waiting, an account at its own
+    /// `max_concurrent` may start later than this.
+    pub fn order(&self) -> Vec<(u64, i64)> {
+        let state = self.state.lock().unwrap();
+        let mut running = state.running.clone();
+        let mut last_served = state.last_served.clone();
+        let mut waiting = state
+            .waiting
+            .iter()
+            .filter(|waiter| !waiter.ready.is_closed())
+            .collect::<Vec<_>>();
+
+        let mut order = Vec::with_capacity(waiting.len());
+        let mut served = state.served;
+        while let Some(index) = pick(&running, &last_served, &waiting, false) {
+            let waiter = waiting.remove(index);
+            *running.entry(waiter.owner).or_default() += 1;
+            served += 1;
+            last_served.insert(waiter.owner, served);
+            order.push((waiter.job, waiter.owner));
+        }
+
+        order
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tokio::fs;
+
+/// Sets the flag when dropped, to stop blocking work a dropped future started.
+pub struct StopOnDrop(pub Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Partial output, removed when dropped unless it has been promoted into place.
+///
+/// Dropping covers failures as well as timeouts, where `Jobs` drops the render future.
+pub struct Staging(pub Option<PathBuf>);
+
+impl Staging {
+    pub fn path(&self) -> &Path {
+        self.0.as_deref().unwrap()
+    }
+
+    pub async fn promote(mut self, to: &Path) -> std::io::Result<()> {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(self.path(), to).await?;
+        self.0 = None;
+        Ok(())
+    }
+
+    /// Swaps the staged output with an existing map, the old map is removed on drop.
+    pub async fn replace(mut self, to: &Path) -> std::io::Result<()> {
+        let old = self.path().with_extension("old");
+        fs::rename(to, &old).await?;
+        if let Err(e) = fs::rename(self.path(), to).await {
+            fs::rename(&old, to).await?;
+            return Err(e);
+        }
+        self.0 = Some(old);
+        Ok(())
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            std::thread::spawn(move || {
+                let _ = std::fs::remove_dir_all(path);
+            });
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
+use goodmorning_services::{
+    bindings::{
+        services::v1::{V1Error, V1Response},
+        structs::*,
+    },
+    functions::{get_user_dir, get_usersys_dir},
+    structs::GMServices,
+    traits::TaskItem,
+};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    functions::{list_files, now, webroot, zip_files},
+    structs::{JobTask, RenderPhase, RenderStatus, Staging, StopOnDrop, V1BlueResponse},
+    values::RENDER_JOBS,
+};
+
+/// Packages a map's webapp into a zip that can be served from any static host.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ExportTask {
+    /// Map to export, relative to the owner's directory.
+    pub from: PathBuf,
+    /// Zip to write, relative to the owner's directory.
+    pub to: PathBuf,
+    pub user: i64,
+    /// Job id assigned by `RenderJobs::register`.
+    #[serde(default)]
+    pub job: u64,
+}
+
+impl JobTask for ExportTask {
+    fn owner(&self) -> i64 {
+        self.user
+    }
+
+    fn job(&self) -> u64 {
+        self.job
+    }
+
+    fn set_job(&mut self, id: u64) {
+        self.job = id;
+    }
+
+    fn result(&self) -> Option<V1BlueResponse> {
+        Some(V1BlueResponse::Exported {
+            path: self.to.to_string_lossy().to_string(),
+        })
+    }
+}
+
+impl ExportTask {
+    async fn export(&self) -> Result<(), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        if renders.is_cancelled(self.job) {
+            return Err("export cancelled".to_string());
+        }
+        renders.set_status(self.job, RenderStatus::Running);
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Scanning;
+            progress.started = Some(now());
+        });
+
+        let from_abs = get_user_dir(self.user, None).join(&self.from);
+        let to_abs = get_user_dir(self.user, None).join(&self.to);
+        let root = webroot(&from_abs).await;
+        let staging = Staging(Some(
+            get_usersys_dir(self.user, Some(GMServices::Blue))
+                .join("staging")
+                .join(format!("{}-export", self.job)),
+        ));
+        let zip = staging.path().join("map.zip");
+        fs::create_dir_all(staging.path())
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let _stop = StopOnDrop(stopped.clone());
+        let (job, zip_path) = (self.job, zip.clone());
+
+        tokio::task::spawn_blocking(move || {
+            // manifests only mean something to GM Blue
+            let files = list_files(&root)?
+                .into_iter()
+                .filter(|file| {
+                    file.components().count() > 1 || !file.to_string_lossy().starts_with("gmblue-")
+                })
+                .collect::<Vec<_>>();
+
+            let renders = RENDER_JOBS.get().unwrap();
+            renders.log(job, format!("Archiving {} files", files.len()));
+            renders.update_progress(job, |progress| {
+                progress.phase = RenderPhase::Archiving;
+                progress.total = files.len() as u64;
+            });
+
+            zip_files(
+                &root,
+                &files,
+                &zip_path,
+                &|| stopped.load(Ordering::Relaxed) || renders.is_cancelled(job),
+                &|done| renders.update_progress(job, |progress| progress.done = done),
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| {
+            renders.log(self.job, format!("Export failed: {e}"));
+            e.to_string()
+        })?;
+
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Finishing;
+            progress.done = progress.total;
+        });
+
+        if fs::try_exists(&to_abs).await.unwrap_or(true) {
+            renders.log(self.job, "Target was created while exporting");
+            return Err("target path occupied".to_string());
+        }
+        if let Some(parent) = to_abs.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        fs::rename(&zip, &to_abs).await.map_err(|e| e.to_string())?;
+
+        renders.log(self.job, "Export finished");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskItem for ExportTask {
+    async fn run(&self, ver: &ApiVer, id: u64) -> CommonRes {
+        match self.export().await {
+            Ok(()) => match ver {
+                ApiVer::V1 => CommonRes::V1(Ok(V1Response::BlueRendered {
+                    newpath: self.to.to_string_lossy().to_string(),
+                    id,
+                })),
+            },
+            Err(content) => match ver {
+                ApiVer::V1 => CommonRes::V1(Err(V1Error::External { content })),
+            },
+        }
+    }
+
+    fn to(&self, _ver: &ApiVer) -> Box<dyn goodmorning_services::bindings::traits::SerdeAny> {
+        Box::new(BlueRenderDisplay {
+            from: self.from.to_string_lossy().to_string(),
+            to: self.to.to_string_lossy().to_string(),
+            preset: "export".to_string(),
+        })
+    }
+}
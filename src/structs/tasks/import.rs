@@ -0,0 +1,228 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
+use goodmorning_services::{
+    bindings::{
+        services::v1::{V1Error, V1Response},
+        structs::*,
+    },
+    functions::{get_user_dir, get_usersys_dir},
+    structs::GMServices,
+    traits::TaskItem,
+};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    functions::{
+        extract_archive, find_webapp_root, import_file, import_settings, list_files, now,
+        validate_webapp, webroot, write_settings, ArchiveKind,
+    },
+    structs::{
+        bluemap_version, ImportManifest, JobTask, RenderPhase, RenderStatus, Staging, StopOnDrop,
+    },
+    values::{RENDER_JOBS, VALUES},
+};
+
+/// Brings a BlueMap webapp rendered elsewhere in as a map.
+///
+/// Only the map data is taken from the import, it is served with the webapp files in
+/// `webapp_path` so nothing the import brings along runs on GM Blue's origin.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ImportTask {
+    /// Webapp folder or archive, relative to the owner's directory.
+    pub from: PathBuf,
+    /// Map to create, relative to the owner's directory.
+    pub to: PathBuf,
+    pub user: i64,
+    /// Job id assigned by `RenderJobs::register`.
+    #[serde(default)]
+    pub job: u64,
+}
+
+impl JobTask for ImportTask {
+    fn owner(&self) -> i64 {
+        self.user
+    }
+
+    fn job(&self) -> u64 {
+        self.job
+    }
+
+    fn set_job(&mut self, id: u64) {
+        self.job = id;
+    }
+}
+
+impl ImportTask {
+    async fn import(&self) -> Result<(), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        if renders.is_cancelled(self.job) {
+            return Err("import cancelled".to_string());
+        }
+        renders.set_status(self.job, RenderStatus::Running);
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Scanning;
+            progress.started = Some(now());
+        });
+
+        let Some(webapp) = VALUES.get().config.webapp_path.clone().map(PathBuf::from) else {
+            return Err("imports are not enabled on this server".to_string());
+        };
+        let from_abs = get_user_dir(self.user, None).join(&self.from);
+        let to_abs = get_user_dir(self.user, None).join(&self.to);
+        let staging_dir = get_usersys_dir(self.user, Some(GMServices::Blue)).join("staging");
+        let staging = Staging(Some(staging_dir.join(format!("{}-import", self.job))));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let _stop = StopOnDrop(stopped.clone());
+        let job = self.job;
+
+        // kept until the import is done, the webapp root is in it
+        let extracted = ArchiveKind::from_path(&from_abs).is_some().then(|| {
+            Staging(Some(
+                staging_dir.join(format!("{}-import.source", self.job)),
+            ))
+        });
+        let root = match &extracted {
+            Some(extracted) => {
+                let (archive, to) = (from_abs.clone(), extracted.path().to_path_buf());
+                let max_size = VALUES.get().config.archive_max_size;
+                let stopped = stopped.clone();
+                renders.log(self.job, "Extracting archive");
+
+                tokio::task::spawn_blocking(move || {
+                    extract_archive(&archive, &to, max_size, &|| {
+                        stopped.load(Ordering::Relaxed) || renders.is_cancelled(job)
+                    })?;
+                    Ok::<_, std::io::Error>(find_webapp_root(&to))
+                })
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| format!("could not extract archive: {e}"))?
+                .ok_or_else(|| "no BlueMap webapp in archive".to_string())?
+            }
+            None => webroot(&from_abs).await,
+        };
+
+        let maps = validate_webapp(&root).await.map_err(|e| e.to_string())?;
+        let settings = import_settings(&root, &maps)
+            .await
+            .map_err(|e| format!("settings.json could not be read: {e}"))?;
+
+        fs::create_dir_all(staging.path())
+            .await
+            .map_err(|e| e.to_string())?;
+        let staged = staging.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            // the server's webapp, without the settings and maps of wherever it was taken from
+            let webapp_files = list_files(&webapp)?
+                .into_iter()
+                .filter(|file| {
+                    let first = file.iter().next().unwrap_or_default().to_string_lossy();
+                    first != "maps" && first != "settings.json" && !first.starts_with("gmblue-")
+                })
+                .collect::<Vec<_>>();
+            let data_files = list_files(&root)?
+                .into_iter()
+                .filter(|file| import_file(file, &maps))
+                .collect::<Vec<_>>();
+
+            let renders = RENDER_JOBS.get().unwrap();
+            renders.log(
+                job,
+                format!("Copying {} files of map data", data_files.len()),
+            );
+            renders.update_progress(job, |progress| {
+                progress.phase = RenderPhase::Rendering;
+                progress.total = (webapp_files.len() + data_files.len()) as u64;
+            });
+
+            let files = webapp_files
+                .iter()
+                .map(|file| (webapp.as_path(), file))
+                .chain(data_files.iter().map(|file| (root.as_path(), file)));
+            for (done, (from, file)) in files.enumerate() {
+                if stopped.load(Ordering::Relaxed) || renders.is_cancelled(job) {
+                    return Err(std::io::Error::other("import stopped"));
+                }
+
+                copy_file(&from.join(file), &staged.join(file))?;
+                renders.update_progress(job, |progress| progress.done = done as u64 + 1);
+            }
+
+            Ok(())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| {
+            renders.log(self.job, format!("Import failed: {e}"));
+            e.to_string()
+        })?;
+
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Finishing;
+            progress.done = progress.total;
+        });
+
+        write_settings(staging.path(), &settings)
+            .await
+            .map_err(|e| e.to_string())?;
+        ImportManifest {
+            from: self.from.clone(),
+            user: self.user,
+            imported: now(),
+            bluemap: bluemap_version(staging.path()).await,
+        }
+        .save(staging.path())
+        .await
+        .map_err(|e| e.to_string())?;
+
+        if fs::try_exists(&to_abs).await.unwrap_or(true) {
+            renders.log(self.job, "Target was created while importing");
+            return Err("target path occupied".to_string());
+        }
+        staging.promote(&to_abs).await.map_err(|e| e.to_string())?;
+
+        renders.log(self.job, "Import finished");
+        Ok(())
+    }
+}
+
+fn copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(from, to)?;
+    Ok(())
+}
+
+#[async_trait]
+impl TaskItem for ImportTask {
+    async fn run(&self, ver: &ApiVer, id: u64) -> CommonRes {
+        match self.import().await {
+            Ok(()) => match ver {
+                ApiVer::V1 => CommonRes::V1(Ok(V1Response::BlueRendered {
+                    newpath: self.to.to_string_lossy().to_string(),
+                    id,
+                })),
+            },
+            Err(content) => match ver {
+                ApiVer::V1 => CommonRes::V1(Err(V1Error::External { content })),
+            },
+        }
+    }
+
+    fn to(&self, _ver: &ApiVer) -> Box<dyn goodmorning_services::bindings::traits::SerdeAny> {
+        Box::new(BlueRenderDisplay {
+            from: self.from.to_string_lossy().to_string(),
+            to: self.to.to_string_lossy().to_string(),
+            preset: "import".to_string(),
+        })
+    }
+}
@@ -1,2 +1,6 @@
 mod render;
 pub use render::*;
+mod export;
+pub use export::*;
+mod import;
+pub use import::*;
@@ -1,16 +1,42 @@
-use std::{fmt::Debug, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use bluemap_singleserve::{Config, Map};
+use bluemap_singleserve::Map;
 use goodmorning_services::{
     bindings::{
         services::v1::{V1Error, V1Response},
         structs::*,
     },
-    functions::get_user_dir,
+    functions::{get_user_dir, get_usersys_dir, has_dotdot},
+    structs::GMServices,
     traits::TaskItem,
 };
 use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    functions::{
+        check_world, clean_snapshots, count_changed_regions, count_regions, count_tiles,
+        dimension_map_id, dimension_name, dir_size, extract_archive, find_world_root, link_dir,
+        map_exists, merge_map, now, override_lines, preset_dimension, preset_path, prune_snapshots,
+        region_dir, snapshots_dir, take_snapshot, valid_dimension, validate_preset, ArchiveKind,
+        USER_PRESET_PREFIX,
+    },
+    structs::{
+        bluemap_version, JobTask, LogTail, RenderArea, RenderManifest, RenderPhase, RenderRecord,
+        RenderStatus, Staging, StopOnDrop,
+    },
+    values::{RENDER_JOBS, VALUES},
+};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RenderTask {
@@ -18,33 +44,448 @@ pub struct RenderTask {
     pub to: PathBuf,
     pub preset: String,
     pub user: i64,
+    /// Render id assigned by `RenderJobs::register`.
+    #[serde(default)]
+    pub job: u64,
+    /// Re-render an existing map in place, only redoing regions changed since the last render.
+    #[serde(default)]
+    pub update: bool,
+    /// Dimensions to render as separate maps of one output, the preset's own dimension if empty.
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    #[serde(default)]
+    pub area: RenderArea,
+    /// Validated against `PRESET_OVERRIDES` before the task is created.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, serde_json::Value>,
+    /// Previous versions to keep as snapshots when replacing a map, 0 to overwrite it.
+    #[serde(default)]
+    pub keep_versions: usize,
 }
 
-#[async_trait]
-impl TaskItem for RenderTask {
-    async fn run(&self, ver: &ApiVer, id: u64) -> CommonRes {
+/// Writes a preset with overrides applied into `dir`, returning its path.
+///
+/// Keeps the original preset's file name, in case it is used as the map id.
+async fn write_preset(dir: &Path, original: &Path, conf: &str) -> Result<PathBuf, String> {
+    let path = dir.join(original.file_name().unwrap_or_default());
+    fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    fs::write(&path, conf).await.map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+impl JobTask for RenderTask {
+    fn owner(&self) -> i64 {
+        self.user
+    }
+
+    fn job(&self) -> u64 {
+        self.job
+    }
+
+    fn set_job(&mut self, id: u64) {
+        self.job = id;
+    }
+}
+
+impl RenderTask {
+    /// An incremental update of `map`, rendered again with what its manifest records.
+    pub fn update_of(
+        user: i64,
+        map: PathBuf,
+        manifest: RenderManifest,
+        keep_versions: usize,
+    ) -> Self {
+        Self {
+            from: manifest.from,
+            to: map,
+            preset: manifest.preset,
+            user,
+            job: 0,
+            update: true,
+            dimensions: manifest.dimensions,
+            area: manifest.area,
+            overrides: manifest.overrides,
+            keep_versions: if manifest.keep_versions {
+                keep_versions
+            } else {
+                0
+            },
+        }
+    }
+
+    async fn render(&self) -> Result<(), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        if renders.is_cancelled(self.job) {
+            return Err("render cancelled".to_string());
+        }
+        let started = now();
+        renders.set_status(self.job, RenderStatus::Running);
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Scanning;
+            progress.started = Some(started);
+        });
+
         let from_abs = get_user_dir(self.user, None).join(&self.from);
         let to_abs = get_user_dir(self.user, None).join(&self.to);
+        let preset = preset_path(self.user, &self.preset);
+        if has_dotdot(Path::new(&self.preset)) {
+            return Err("preset not found".to_string());
+        }
+        if self.preset.starts_with(USER_PRESET_PREFIX) {
+            // saved presets were checked on save, but possibly under older rules
+            let conf = fs::read_to_string(&preset)
+                .await
+                .map_err(|e| e.to_string())?;
+            validate_preset(&conf).map_err(|e| e.to_string())?;
+        }
+        let staging_dir = get_usersys_dir(self.user, Some(GMServices::Blue)).join("staging");
+        let staging = Staging(Some(staging_dir.join(self.job.to_string())));
 
-        match Map::render(
-            &from_abs,
-            &to_abs,
-            &bluemap_singleserve::MasterConfig::get()
-                .templates
-                .join(&self.preset),
-        )
-        .await
+        let dimensions = if self.dimensions.is_empty() {
+            vec![preset_dimension(&preset).await]
+        } else {
+            self.dimensions.clone()
+        };
+        // manifests and presets are files the user can edit, only ids get near a path or preset
+        if let Some(dimension) = dimensions
+            .iter()
+            .find(|dimension| !valid_dimension(dimension))
+        {
+            return Err(format!("{dimension} is not a valid dimension"));
+        }
+
+        // the extracted copy of an archive lives until the render is done
+        let (from_abs, _extracted) = if ArchiveKind::from_path(&from_abs).is_some() {
+            let (world, extracted) = self.extract(&from_abs, &staging_dir, &dimensions).await?;
+            (world, Some(extracted))
+        } else {
+            (from_abs, None)
+        };
+
+        let mut regions = 0;
+        for dimension in dimensions.iter() {
+            regions += count_regions(&region_dir(&from_abs, dimension), &self.area).await;
+        }
+
+        // an update rendered with other settings than the map was has to redo every region, or the
+        // map would end up half rendered with each
+        let manifest = if self.update {
+            RenderManifest::load(&to_abs).await
+        } else {
+            None
+        };
+        let since = match manifest {
+            Some(manifest) if manifest.renders_like(self) => Some(manifest.started),
+            Some(_) => {
+                renders.log(
+                    self.job,
+                    "Settings changed since the last render, rendering every region again",
+                );
+                None
+            }
+            None => None,
+        };
+
+        if let Some(since) = since {
+            let mut changed = 0;
+            for dimension in dimensions.iter() {
+                changed +=
+                    count_changed_regions(&region_dir(&from_abs, dimension), since, &self.area)
+                        .await;
+            }
+
+            if changed == 0 {
+                renders.log(self.job, "No regions changed since the last render");
+                return Ok(());
+            }
+
+            renders.log(
+                self.job,
+                format!("{changed} of {regions} regions changed since the last render"),
+            );
+            regions = changed;
+        }
+
+        renders.set_regions(self.job, regions);
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Rendering;
+            // a hires tile is 501 blocks wide, slightly smaller than a 512 block region
+            progress.total = regions + regions / 20;
+        });
+
+        let staging_path = staging.path().to_path_buf();
+        fs::create_dir_all(&staging_dir)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let conf = fs::read_to_string(&preset)
+            .await
+            .map_err(|e| e.to_string())?;
+        let presets = Staging(Some(staging_dir.join(format!("{}-presets", self.job))));
+        let overrides = override_lines(&self.overrides).map_err(|e| e.to_string())?
+            + &self.area.preset_overrides();
+
+        if self.dimensions.is_empty() {
+            renders.log(
+                self.job,
+                format!(
+                    "Rendering {regions} regions of {} with {}",
+                    dimensions[0], self.preset
+                ),
+            );
+
+            let preset = if overrides.is_empty() {
+                preset.clone()
+            } else {
+                write_preset(presets.path(), &preset, &format!("{conf}\n{overrides}")).await?
+            };
+
+            if since.is_some() {
+                // BlueMap keeps its render state in the output, starting from the current map
+                // means only the changed regions get rendered again, linking its tiles keeps the
+                // update from taking up the map's size again
+                link_dir(&to_abs, &staging_path)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            self.render_map(&from_abs, &staging_path, &preset, 0)
+                .await?;
+        } else {
+            if self.update {
+                renders.log(
+                    self.job,
+                    "Rendering every dimension again, incremental updates need a single dimension",
+                );
+            }
+
+            let mut done = 0;
+            for dimension in dimensions.iter() {
+                let id = dimension_map_id(dimension);
+                renders.log(
+                    self.job,
+                    format!("Rendering {dimension} with {}", self.preset),
+                );
+
+                let dimension_preset = write_preset(
+                    &presets.path().join(&id),
+                    &preset,
+                    &format!(
+                        "{conf}\n{overrides}dimension: \"{dimension}\"\nname: \"{}\"\n",
+                        dimension_name(dimension)
+                    ),
+                )
+                .await?;
+
+                let output = Staging(Some(staging_dir.join(format!("{}-{id}", self.job))));
+                self.render_map(&from_abs, output.path(), &dimension_preset, done)
+                    .await?;
+                done += count_tiles(output.path()).await;
+
+                merge_map(output.path(), &staging_path, &id)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        renders.update_progress(self.job, |progress| {
+            progress.phase = RenderPhase::Finishing;
+            progress.done = progress.total;
+        });
+
+        // carried over so the map keeps its snapshots, and given the first time it keeps a version
+        let map_id = match RenderManifest::load(&to_abs)
+            .await
+            .and_then(|manifest| manifest.map_id)
         {
+            Some(map_id) => Some(map_id),
+            None if self.keep_versions > 0 => Some(fastrand::u64(..1 << 53)),
+            None => None,
+        };
+
+        RenderManifest {
+            from: self.from.clone(),
+            preset: self.preset.clone(),
+            user: self.user,
+            started,
+            finished: now(),
+            bluemap: bluemap_version(&staging_path).await,
+            dimensions: self.dimensions.clone(),
+            area: self.area,
+            overrides: self.overrides.clone(),
+            keep_versions: self.keep_versions > 0,
+            map_id,
+        }
+        .save(&staging_path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let keep_version = self.keep_versions > 0 && map_exists(&to_abs).await;
+        if let Some(map_id) = map_id.filter(|_| keep_version) {
+            self.keep_version(staging, &to_abs, map_id).await?;
+        } else if self.update {
+            staging.replace(&to_abs).await.map_err(|e| e.to_string())?;
+        } else if fs::try_exists(&to_abs).await.unwrap_or(true) {
+            renders.log(self.job, "Target was created while rendering");
+            return Err("target path occupied".to_string());
+        } else {
+            staging.promote(&to_abs).await.map_err(|e| e.to_string())?;
+        }
+
+        renders.log(self.job, "Render finished");
+        clean_snapshots(self.user).await;
+
+        let record = RenderRecord {
+            user: self.user,
+            regions,
+            duration: now().saturating_sub(started),
+            size: dir_size(&to_abs).await,
+            update: self.update,
+            finished: now(),
+        };
+        if let Err(e) = record.save().await {
+            log::warn!("failed to record render {}: {e}", self.job);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the current map into its snapshots and the staged output into its place, then prunes
+    /// snapshots past `keep_versions`.
+    async fn keep_version(&self, staging: Staging, to: &Path, map_id: u64) -> Result<(), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        let id = take_snapshot(self.user, &self.to, map_id)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Err(e) = staging.promote(to).await {
+            let _ = fs::rename(snapshots_dir(self.user, map_id).join(id.to_string()), to).await;
+            return Err(e.to_string());
+        }
+        renders.log(self.job, "Previous version kept as a snapshot");
+
+        if let Err(e) = prune_snapshots(self.user, map_id, self.keep_versions).await {
+            log::warn!("failed to prune snapshots of render {}: {e}", self.job);
+        }
+        Ok(())
+    }
+
+    /// Extracts an archive source next to the staging output, returning the world in it and the
+    /// guard removing the extracted copy.
+    async fn extract(
+        &self,
+        archive: &Path,
+        staging_dir: &Path,
+        dimensions: &[String],
+    ) -> Result<(PathBuf, Staging), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        renders.log(self.job, "Extracting archive");
+
+        let extracted = Staging(Some(staging_dir.join(format!("{}-source", self.job))));
+        let (archive, to, job) = (
+            archive.to_path_buf(),
+            extracted.path().to_path_buf(),
+            self.job,
+        );
+        let max_size = VALUES.get().config.archive_max_size;
+
+        // tells the blocking extraction to stop if the render future is dropped, e.g. on timeout
+        let stopped = Arc::new(AtomicBool::new(false));
+        let _stop = StopOnDrop(stopped.clone());
+
+        let world = tokio::task::spawn_blocking(move || {
+            extract_archive(&archive, &to, max_size, &|| {
+                stopped.load(Ordering::Relaxed) || RENDER_JOBS.get().unwrap().is_cancelled(job)
+            })?;
+            Ok::<_, std::io::Error>(find_world_root(&to))
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("could not extract archive: {e}"))?
+        .ok_or("no level.dat in archive")?;
+
+        check_world(&world, dimensions)
+            .await
+            .map_err(|e| e.to_string())?;
+        renders.log(self.job, "Archive extracted");
+
+        Ok((world, extracted))
+    }
+
+    /// Runs BlueMap into `output`, stopping early if the job is cancelled.
+    ///
+    /// `done` is the number of tiles earlier renders of this job have written, it is added to the
+    /// tiles counted in `output` when reporting progress.
+    async fn render_map(
+        &self,
+        from: &Path,
+        output: &Path,
+        preset: &Path,
+        done: u64,
+    ) -> Result<(), String> {
+        let renders = RENDER_JOBS.get().unwrap();
+        // with more renders at once the log would hold other accounts' renders too
+        let bluemap_log = {
+            let config = &VALUES.get().config;
+            config
+                .bluemap_log
+                .clone()
+                .filter(|_| config.max_renders == Some(1))
+        };
+        let mut bluemap_log = match bluemap_log {
+            Some(path) => Some(LogTail::new(PathBuf::from(path)).await),
+            None => None,
+        };
+        let render = Map::render(from, output, preset);
+        tokio::pin!(render);
+        let mut sample = tokio::time::interval(Duration::from_secs(2));
+
+        loop {
+            tokio::select! {
+                res = &mut render => {
+                    if let Some(bluemap_log) = bluemap_log.take() {
+                        for line in bluemap_log.finish().await {
+                            renders.log(self.job, line);
+                        }
+                    }
+                    if let Err(e) = res {
+                        renders.log(self.job, format!("Render failed: {e}"));
+                        return Err(e.to_string());
+                    }
+                    return Ok(());
+                }
+                _ = renders.cancelled(self.job) => {
+                    renders.log(self.job, "Render cancelled");
+                    return Err("render cancelled".to_string());
+                }
+                _ = sample.tick() => {
+                    if let Some(bluemap_log) = &mut bluemap_log {
+                        for line in bluemap_log.lines().await {
+                            renders.log(self.job, line);
+                        }
+                    }
+                    let done = done + count_tiles(output).await;
+                    renders.update_progress(self.job, |progress| {
+                        progress.done = done;
+                        progress.total = progress.total.max(done);
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TaskItem for RenderTask {
+    async fn run(&self, ver: &ApiVer, id: u64) -> CommonRes {
+        match self.render().await {
             Ok(()) => match ver {
                 ApiVer::V1 => CommonRes::V1(Ok(V1Response::BlueRendered {
                     newpath: self.to.to_string_lossy().to_string(),
                     id,
                 })),
             },
-            Err(e) => match ver {
-                ApiVer::V1 => CommonRes::V1(Err(V1Error::External {
-                    content: e.to_string(),
-                })),
+            Err(content) => match ver {
+                ApiVer::V1 => CommonRes::V1(Err(V1Error::External { content })),
             },
         }
     }
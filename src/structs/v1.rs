@@ -0,0 +1,378 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+};
+
+use goodmorning_services::bindings::services::v1::V1DirItem;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ImportManifest, PresetInfo, QueueEstimate, RenderArea, RenderEstimate, RenderManifest,
+    RenderProgress, RenderSchedule, RenderStatus, Snapshot, WatchState, WorldInfo,
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueRender {
+    pub token: String,
+    /// A world folder, or a `.zip`, `.tar` or `.tar.gz` archive of one.
+    pub from: String,
+    pub to: String,
+    pub preset: String,
+    /// Return the job id straight away instead of waiting for the render to finish.
+    #[serde(default)]
+    pub background: bool,
+    /// Re-render an existing map at `to` instead of rendering to a new path.
+    #[serde(default)]
+    pub update: bool,
+    /// Dimensions to render into one output with a map each, e.g. `["overworld", "nether", "end"]`.
+    /// Only the preset's own dimension is rendered if empty.
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    /// Only estimate the render, nothing is queued.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Limits the render to part of the world.
+    #[serde(default)]
+    pub area: V1BlueArea,
+    /// Preset settings to change for this render only, limited to `PRESET_OVERRIDES`.
+    /// Carried over from the map's last render when updating, unless given, an empty object
+    /// clears them.
+    #[serde(default)]
+    pub overrides: Option<BTreeMap<String, serde_json::Value>>,
+    /// Keep the map being replaced as a snapshot, also allowing a new render over an existing map.
+    /// Carried over from the map's last render when updating, unless given.
+    #[serde(default)]
+    pub keep_versions: Option<bool>,
+}
+
+/// Render area as given to `/render`, either as a bounding box or as a center and radius.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct V1BlueArea {
+    pub min_x: Option<i32>,
+    pub max_x: Option<i32>,
+    pub min_z: Option<i32>,
+    pub max_z: Option<i32>,
+    pub center_x: Option<i32>,
+    pub center_z: Option<i32>,
+    pub radius: Option<u32>,
+    pub min_y: Option<i32>,
+    pub max_y: Option<i32>,
+}
+
+impl V1BlueArea {
+    pub fn resolve(&self) -> Result<RenderArea, V1BlueError> {
+        let invalid = |reason: &str| V1BlueError::InvalidArea {
+            reason: reason.to_string(),
+        };
+
+        let mut area = RenderArea {
+            min_x: self.min_x,
+            max_x: self.max_x,
+            min_z: self.min_z,
+            max_z: self.max_z,
+            min_y: self.min_y,
+            max_y: self.max_y,
+        };
+
+        match self.radius {
+            Some(radius) => {
+                if [self.min_x, self.max_x, self.min_z, self.max_z]
+                    .iter()
+                    .any(Option::is_some)
+                {
+                    return Err(invalid("use either a bounding box or a radius, not both"));
+                }
+
+                let radius = radius.min(i32::MAX as u32) as i32;
+                let (x, z) = (self.center_x.unwrap_or(0), self.center_z.unwrap_or(0));
+                area.min_x = Some(x.saturating_sub(radius));
+                area.max_x = Some(x.saturating_add(radius));
+                area.min_z = Some(z.saturating_sub(radius));
+                area.max_z = Some(z.saturating_add(radius));
+            }
+            None if self.center_x.is_some() || self.center_z.is_some() => {
+                return Err(invalid("a center needs a radius"))
+            }
+            None => {}
+        }
+
+        for (min, max, axis) in [
+            (area.min_x, area.max_x, "x"),
+            (area.min_z, area.max_z, "z"),
+            (area.min_y, area.max_y, "y"),
+        ] {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    return Err(invalid(&format!("min {axis} is greater than max {axis}")));
+                }
+            }
+        }
+
+        Ok(area)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueImport {
+    pub token: String,
+    /// A BlueMap webapp folder, or a `.zip`, `.tar` or `.tar.gz` archive of one.
+    pub from: String,
+    pub to: String,
+    /// Return the job id straight away instead of waiting for the import.
+    #[serde(default)]
+    pub background: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueExport {
+    pub token: String,
+    /// Map to export, under `blue/`.
+    pub from: String,
+    /// Zip to write, relative to the user's directory like a render's `from`, `.zip` is appended if
+    /// missing.
+    pub to: String,
+    /// Return the job id straight away instead of waiting for the export.
+    #[serde(default)]
+    pub background: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueSnapshot {
+    pub token: String,
+    /// Map the snapshot was taken of, under `blue/`.
+    pub path: String,
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueWatch {
+    pub token: String,
+    /// Map to update automatically, under `blue/`.
+    pub path: String,
+    pub state: WatchState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueScheduleSave {
+    pub token: String,
+    /// Schedule to edit, a new one is created if not given.
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// As for `/render`.
+    pub from: String,
+    pub to: String,
+    pub preset: String,
+    /// A cron expression or a phrase such as `daily at 04:00`, in UTC.
+    pub schedule: String,
+    #[serde(default)]
+    pub paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueScheduleDelete {
+    pub token: String,
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueJob {
+    pub token: String,
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BlueToken {
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BluePresetSave {
+    pub token: String,
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct V1BluePresetDelete {
+    pub token: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum V1BlueResponse {
+    /// `V1Response::DirContent` with the manifests of the maps in the directory, keyed by name.
+    #[serde(rename = "dir content")]
+    DirContent {
+        content: Vec<V1DirItem>,
+        manifests: HashMap<String, RenderManifest>,
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
+        imports: HashMap<String, ImportManifest>,
+    },
+    #[serde(rename = "blue render estimate")]
+    RenderEstimate {
+        #[serde(flatten)]
+        estimate: RenderEstimate,
+    },
+    #[serde(rename = "blue imported")]
+    Imported { path: String },
+    #[serde(rename = "blue exported")]
+    Exported { path: String },
+    #[serde(rename = "blue render queued")]
+    RenderQueued { id: u64 },
+    #[serde(rename = "blue export queued")]
+    ExportQueued { id: u64 },
+    #[serde(rename = "blue import queued")]
+    ImportQueued { id: u64 },
+    #[serde(rename = "blue render cancelled")]
+    RenderCancelled { id: u64 },
+    #[serde(rename = "blue render status")]
+    RenderStatus {
+        id: u64,
+        status: RenderStatus,
+        progress: RenderProgress,
+    },
+    #[serde(rename = "blue queue estimate")]
+    QueueEstimate {
+        id: u64,
+        #[serde(flatten)]
+        estimate: QueueEstimate,
+    },
+    /// `V1Response::BluePresets` with metadata for every preset, and the user's own presets,
+    /// which are passed to the render endpoint as `user/<name>`.
+    #[serde(rename = "blue presets")]
+    Presets {
+        default: String,
+        all: Vec<String>,
+        user: Vec<String>,
+        info: Vec<PresetInfo>,
+    },
+    #[serde(rename = "blue preset content")]
+    PresetContent { name: String, content: String },
+    #[serde(rename = "blue preset saved")]
+    PresetSaved { name: String },
+    #[serde(rename = "blue preset deleted")]
+    PresetDeleted { name: String },
+    #[serde(rename = "blue world info")]
+    WorldInfo {
+        #[serde(flatten)]
+        info: WorldInfo,
+    },
+    #[serde(rename = "blue reloaded")]
+    Reloaded { presets: usize },
+    /// Previous versions of a map, newest first, and how many are kept.
+    #[serde(rename = "blue snapshots")]
+    Snapshots {
+        snapshots: Vec<Snapshot>,
+        limit: usize,
+    },
+    #[serde(rename = "blue snapshot deleted")]
+    SnapshotDeleted { id: u64 },
+    #[serde(rename = "blue schedules")]
+    Schedules { schedules: Vec<RenderSchedule> },
+    #[serde(rename = "blue schedule saved")]
+    ScheduleSaved { schedule: RenderSchedule },
+    #[serde(rename = "blue schedule deleted")]
+    ScheduleDeleted { id: u64 },
+    #[serde(rename = "blue watch")]
+    Watch {
+        state: WatchState,
+        last_queued: Option<u64>,
+        last_job: Option<u64>,
+    },
+    #[serde(rename = "error")]
+    Error { kind: V1BlueError },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum V1BlueError {
+    #[serde(rename = "job not found")]
+    JobNotFound,
+    #[serde(rename = "job finished")]
+    JobFinished,
+    #[serde(rename = "preset not found")]
+    PresetNotFound,
+    #[serde(rename = "invalid preset")]
+    InvalidPreset { reason: String },
+    #[serde(rename = "too many presets")]
+    TooManyPresets { limit: usize },
+    #[serde(rename = "dimension not found")]
+    DimensionNotFound { dimension: String },
+    #[serde(rename = "invalid dimension")]
+    InvalidDimension { dimension: String },
+    #[serde(rename = "invalid area")]
+    InvalidArea { reason: String },
+    #[serde(rename = "invalid override")]
+    InvalidOverride { key: String, reason: String },
+    #[serde(rename = "not a world")]
+    NotAWorld,
+    #[serde(rename = "invalid level dat")]
+    InvalidLevelDat { reason: String },
+    #[serde(rename = "unsupported world version")]
+    UnsupportedWorldVersion {
+        data_version: i64,
+        version: Option<String>,
+    },
+    #[serde(rename = "no estimate")]
+    NoEstimate { reason: String },
+    #[serde(rename = "invalid import")]
+    InvalidImport { reason: String },
+    #[serde(rename = "snapshot not found")]
+    SnapshotNotFound,
+    /// The account's tier keeps no snapshots, so `keep_versions` cannot be used.
+    #[serde(rename = "snapshots disabled")]
+    SnapshotsDisabled,
+    /// Only maps rendered by GM Blue record the world they came from.
+    #[serde(rename = "not rendered")]
+    NotRendered,
+    #[serde(rename = "invalid schedule")]
+    InvalidSchedule { reason: String },
+    #[serde(rename = "schedule not found")]
+    ScheduleNotFound,
+    #[serde(rename = "too many schedules")]
+    TooManySchedules { limit: usize },
+    /// The account already has as many jobs waiting as its queue limit allows.
+    #[serde(rename = "queue full")]
+    QueueFull { limit: usize },
+    #[serde(rename = "reload failed")]
+    ReloadFailed { reason: String },
+}
+
+impl V1BlueError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::JobNotFound => 404,
+            Self::JobFinished => 409,
+            Self::PresetNotFound => 404,
+            Self::InvalidPreset { .. } => 400,
+            Self::TooManyPresets { .. } => 400,
+            Self::DimensionNotFound { .. } => 404,
+            Self::InvalidDimension { .. } => 400,
+            Self::InvalidArea { .. } => 400,
+            Self::InvalidOverride { .. } => 400,
+            Self::NotAWorld => 400,
+            Self::InvalidLevelDat { .. } => 400,
+            Self::UnsupportedWorldVersion { .. } => 400,
+            Self::NoEstimate { .. } => 400,
+            Self::InvalidImport { .. } => 400,
+            Self::SnapshotNotFound => 404,
+            Self::SnapshotsDisabled => 403,
+            Self::NotRendered => 400,
+            Self::InvalidSchedule { .. } => 400,
+            Self::ScheduleNotFound => 404,
+            Self::TooManySchedules { .. } => 400,
+            Self::QueueFull { .. } => 429,
+            Self::ReloadFailed { .. } => 500,
+        }
+    }
+}
+
+impl Display for V1BlueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).unwrap())
+    }
+}
+
+impl Error for V1BlueError {}
@@ -0,0 +1,103 @@
+use std::{error::Error, path::PathBuf};
+
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::values::WATCHES;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchState {
+    On,
+    Off,
+    Paused,
+}
+
+impl WatchState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::On => "on",
+            Self::Off => "off",
+            Self::Paused => "paused",
+        }
+    }
+}
+
+/// A map re-rendered whenever the world it was rendered from changes.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapWatch {
+    pub user: i64,
+    /// Relative to the owner's directory.
+    pub map: PathBuf,
+    pub paused: bool,
+    /// When the last update was queued, in seconds since epoch.
+    pub last_queued: Option<u64>,
+    /// Job id of the last update queued.
+    pub last_job: Option<u64>,
+}
+
+impl MapWatch {
+    pub fn state(&self) -> WatchState {
+        if self.paused {
+            WatchState::Paused
+        } else {
+            WatchState::On
+        }
+    }
+
+    pub async fn find(user: i64, map: &str) -> Result<Option<Self>, Box<dyn Error>> {
+        Ok(WATCHES
+            .get()
+            .unwrap()
+            .find_one(doc! { "user": user, "map": map })
+            .await?)
+    }
+
+    pub async fn all() -> Result<Vec<Self>, Box<dyn Error>> {
+        let mut watches = Vec::new();
+        let mut cursor = WATCHES.get().unwrap().find(doc! {}).await?;
+        while let Some(watch) = cursor.next().await {
+            watches.push(watch?);
+        }
+        Ok(watches)
+    }
+
+    pub async fn save(&self) -> Result<(), Box<dyn Error>> {
+        WATCHES
+            .get()
+            .unwrap()
+            .replace_one(
+                doc! { "user": self.user, "map": self.map.to_string_lossy().as_ref() },
+                self,
+            )
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    /// Records an update being queued, without undoing a pause or removal made meanwhile.
+    pub async fn set_queued(&self) -> Result<(), Box<dyn Error>> {
+        WATCHES
+            .get()
+            .unwrap()
+            .update_one(
+                doc! { "user": self.user, "map": self.map.to_string_lossy().as_ref() },
+                doc! { "$set": {
+                    "last_queued": self.last_queued.map(|queued| queued as i64),
+                    "last_job": self.last_job.map(|job| job as i64),
+                } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(user: i64, map: &str) -> Result<(), Box<dyn Error>> {
+        WATCHES
+            .get()
+            .unwrap()
+            .delete_one(doc! { "user": user, "map": map })
+            .await?;
+        Ok(())
+    }
+}
@@ -1,20 +1,54 @@
-use std::{ffi::OsStr, fs, path::PathBuf, sync::OnceLock};
+use std::{
+    error::Error,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
 
 use bluemap_singleserve::{Config, MasterConfig};
-use goodmorning_services::{functions::parse_path, traits::ConfigTrait, SELF_ADDR};
+use goodmorning_services::{functions::parse_path, traits::ConfigTrait, ACCOUNTS, SELF_ADDR};
+use mongodb::Collection;
 
-use crate::structs::BlueConfig;
+use crate::{
+    functions::global_preset_info,
+    structs::{
+        BlueConfig, JobRecord, MapWatch, PresetInfo, Reloadable, RenderJobs, RenderRecord,
+        RenderSchedule,
+    },
+};
 
-pub static BLUE_CONFIG: OnceLock<BlueConfig> = OnceLock::new();
+/// The blue config and everything derived from it, swapped as one on reload.
+pub static VALUES: Reloadable<Values> = Reloadable::new();
 
-pub static TOPBAR_URLS: OnceLock<String> = OnceLock::new();
-pub static PFP_DEFAULT: OnceLock<PathBuf> = OnceLock::new();
 pub static CSP_BASE: OnceLock<String> = OnceLock::new();
-pub static PRESETS: OnceLock<Vec<String>> = OnceLock::new();
+pub static RENDER_JOBS: OnceLock<RenderJobs> = OnceLock::new();
+pub static RENDER_HISTORY: OnceLock<Collection<RenderRecord>> = OnceLock::new();
+pub static WATCHES: OnceLock<Collection<MapWatch>> = OnceLock::new();
+pub static SCHEDULES: OnceLock<Collection<RenderSchedule>> = OnceLock::new();
+pub static JOB_RECORDS: OnceLock<Collection<JobRecord>> = OnceLock::new();
+
+/// Held while a reload loads and applies, so an older load never replaces a newer one.
+static RELOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// Everything `reload` swaps in, built and validated before any of it is applied.
+pub struct Values {
+    pub config: BlueConfig,
+    pub pfp_default: PathBuf,
+    pub presets: Vec<String>,
+    pub preset_info: Vec<PresetInfo>,
+    pub topbar_urls: String,
+}
 
 pub fn init() {
-    let _ = BLUE_CONFIG.set(*BlueConfig::load().unwrap());
-    let _ = PFP_DEFAULT.set(parse_path(BLUE_CONFIG.get().unwrap().pfp_default.clone()));
+    let _ = RENDER_JOBS.set(RenderJobs::default());
+
+    let accounts = ACCOUNTS.get().unwrap();
+    let database = accounts.client().database(&accounts.namespace().db);
+    let _ = RENDER_HISTORY.set(database.collection("blue_renders"));
+    let _ = WATCHES.set(database.collection("blue_watches"));
+    let _ = SCHEDULES.set(database.collection("blue_schedules"));
+    let _ = JOB_RECORDS.set(database.collection("blue_jobs"));
 
     CSP_BASE
         .set(format!(
@@ -23,44 +57,126 @@ pub fn init() {
         ))
         .unwrap();
 
-    let mut presets = fs::read_dir(&MasterConfig::get().templates)
-        .unwrap()
-        .filter_map(|entry| {
-            if entry.as_ref().unwrap().path().extension() == Some(OsStr::new("conf")) {
-                Some(
-                    entry
-                        .unwrap()
-                        .path()
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string(),
-                )
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
+    match load(false) {
+        Ok(values) => VALUES.set(values),
+        Err(e) => {
+            log::error!("could not load blue config, not starting: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Re-reads the blue config and the preset directory and swaps them in.
+///
+/// Nothing is applied if any of it fails to load, the running values stay as they were. `port`
+/// and `log` are only read on startup, changes to them need a restart.
+pub async fn reload() -> Result<(), String> {
+    tokio::task::spawn_blocking(|| reload_blocking().map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn reload_blocking() -> Result<(), Box<dyn Error>> {
+    let _lock = RELOAD_LOCK.lock().unwrap();
+    let reloaded = load(true)?;
+
+    let current = VALUES.get();
+    if current.config.port != reloaded.config.port {
+        log::warn!("port changed in config, restart the server to apply it");
+    }
+
+    let presets = reloaded.presets.len();
+    VALUES.set(reloaded);
+    // a raised max_renders can start waiting jobs straight away
+    RENDER_JOBS.get().unwrap().slots.refresh();
+    log::info!("reloaded blue config and {presets} presets");
+
+    Ok(())
+}
+
+/// Loads the values, with `strict` refusing settings that are only warned about on startup.
+///
+/// The server has always started with those, a deployment that started before keeps starting.
+fn load(strict: bool) -> Result<Values, Box<dyn Error>> {
+    let config = *BlueConfig::load()?;
+    let lenient = |reason: String| -> Result<(), Box<dyn Error>> {
+        if strict {
+            return Err(reason.into());
+        }
+        log::warn!("{reason}");
+        Ok(())
+    };
+
+    if !Path::new(&config.static_path).is_dir() {
+        lenient(format!(
+            "static path {} is not a directory",
+            config.static_path
+        ))?;
+    }
+
+    if let Some(webapp) = &config.webapp_path {
+        if !Path::new(webapp).join("index.html").is_file() {
+            return Err(format!("webapp path {webapp} has no index.html").into());
+        }
+    }
+
+    if config.render_timeout == 0 {
+        lenient("render_timeout must be greater than 0".to_string())?;
+    }
+
+    if config.watch_interval == 0 {
+        return Err("watch_interval must be greater than 0".into());
+    }
+
+    if config.max_renders == Some(0) {
+        return Err("max_renders must be greater than 0".into());
+    }
+
+    if config.bluemap_log.is_some() && config.max_renders != Some(1) {
+        return Err(
+            "bluemap_log needs max_renders to be 1, or renders would log each other".into(),
+        );
+    }
+
+    let mut presets = Vec::new();
+    for entry in fs::read_dir(&MasterConfig::get().templates)? {
+        let path = entry?.path();
+        if path.extension() == Some(OsStr::new("conf")) {
+            presets.push(path.file_name().unwrap().to_string_lossy().to_string());
+        }
+    }
     presets.sort();
 
-    PRESETS.set(presets).unwrap();
-
-    TOPBAR_URLS
-        .set(
-            BLUE_CONFIG
-                .get()
-                .unwrap()
-                .topbar_urls
-                .iter()
-                .map(|item| {
-                    format!(
-                        r#"<a href="{}" class="top-bar-link">{}</a>"#,
-                        html_escape::encode_safe(&item.url),
-                        html_escape::encode_safe(&item.label)
-                    )
-                })
-                .collect::<Vec<_>>()
-                .join(""),
-        )
-        .unwrap();
+    if !presets.contains(&config.default_preset) {
+        lenient(format!(
+            "default preset {} does not exist",
+            config.default_preset
+        ))?;
+    }
+
+    let preset_info = presets
+        .iter()
+        .map(|name| global_preset_info(name))
+        .collect();
+
+    let topbar_urls = config
+        .topbar_urls
+        .iter()
+        .map(|item| {
+            format!(
+                r#"<a href="{}" class="top-bar-link">{}</a>"#,
+                html_escape::encode_safe(&item.url),
+                html_escape::encode_safe(&item.label)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+
+    Ok(Values {
+        pfp_default: parse_path(config.pfp_default.clone()),
+        config,
+        presets,
+        preset_info,
+        topbar_urls,
+    })
 }
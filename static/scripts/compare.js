@@ -0,0 +1,84 @@
+let controls = document.getElementById("compare-controls");
+let map = controls.getAttribute("map");
+let compare = document.getElementById("compare");
+let versionA = document.getElementById("version-a");
+let versionB = document.getElementById("version-b");
+let mode = document.getElementById("mode");
+let sync = document.getElementById("sync");
+let slider = document.getElementById("slider");
+let viewerA = document.getElementById("viewer-a");
+let viewerB = document.getElementById("viewer-b");
+
+function viewerSrc(version) {
+    return version === "" ? `/fs/${map}/map` : `/snapshot/${version}/${map}/map`;
+}
+
+function updateQuery() {
+    let params = new URLSearchParams();
+    if (versionA.value !== "") params.set("a", versionA.value);
+    if (versionB.value !== "") params.set("b", versionB.value);
+    if (mode.value === "slider") params.set("slider", "true");
+    history.replaceState(null, "", `${window.location.pathname}?${params}`);
+}
+
+function showMode() {
+    let sliding = mode.value === "slider";
+    compare.classList.toggle("sliding", sliding);
+    slider.classList.toggle("hide", !sliding);
+    viewerB.style.clipPath = sliding ? `inset(0 0 0 ${slider.value}%)` : "";
+}
+
+versionA.onchange = () => {
+    viewerA.src = viewerSrc(versionA.value);
+    updateQuery();
+};
+versionB.onchange = () => {
+    viewerB.src = viewerSrc(versionB.value);
+    updateQuery();
+};
+mode.onchange = () => {
+    showMode();
+    updateQuery();
+};
+slider.oninput = showMode;
+
+viewerA.src = viewerSrc(versionA.value);
+viewerB.src = viewerSrc(versionB.value);
+showMode();
+
+// BlueMap keeps the camera in the address hash as "map:x:y:z:distance:...", the position is copied
+// from the viewer last pointed at to the other one, each keeping its own map
+let leader = viewerA;
+let lastHash = new Map();
+
+for (let viewer of [viewerA, viewerB]) {
+    viewer.addEventListener("pointerenter", () => (leader = viewer));
+    viewer.addEventListener("load", () => lastHash.delete(viewer));
+}
+
+function readHash(viewer) {
+    try {
+        return viewer.contentWindow.location.hash;
+    } catch (_) {
+        return "";
+    }
+}
+
+setInterval(() => {
+    if (!sync.checked) return;
+
+    let follower = leader === viewerA ? viewerB : viewerA;
+    let hash = readHash(leader);
+    if (hash === "" || hash === lastHash.get(leader)) return;
+    lastHash.set(leader, hash);
+
+    let position = hash.substring(hash.indexOf(":"));
+    let followerHash = readHash(follower);
+    let followerMap = followerHash === "" ? hash.substring(1, hash.indexOf(":")) : followerHash.substring(1).split(":")[0];
+    let target = `#${followerMap}${position}`;
+
+    if (followerHash !== target) {
+        follower.contentWindow.location.hash = target;
+        lastHash.set(follower, target);
+    }
+}, 200);
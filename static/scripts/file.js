@@ -21,3 +21,150 @@ switch (window.location.pathname.split(".").pop()) {
         });
 }
 
+
+let rerender = document.getElementById("rerender");
+
+if (rerender) {
+    rerender.onclick = () => {
+        let params = new URLSearchParams({
+            source: rerender.getAttribute("source"),
+            target: rerender.getAttribute("target"),
+            update: "true",
+        });
+        window.location.href = `/render?${params}`;
+    };
+}
+
+let exportButton = document.getElementById("export");
+let exportStatus = document.getElementById("export-status");
+
+function getToken() {
+    const value = `; ${document.cookie}`;
+    const parts = value.split(`; token=`);
+    if (parts.length === 2) return parts.pop().split(";").shift();
+}
+
+function exportFinished(text) {
+    exportStatus.innerText = text;
+    exportButton.removeAttribute("disabled");
+}
+
+function pollExport(id) {
+    fetch(`/api/blue/v1/status/${getToken()}/${id}`)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.type == "error") {
+                exportFinished(`Export failed: ${JSON.stringify(data.kind)}`);
+                return;
+            }
+
+            switch (data.status.type) {
+                case "succeeded":
+                    exportFinished(`Exported to ${data.status.result.path}`);
+                    return;
+                case "failed":
+                    exportFinished(`Export failed: ${JSON.stringify(data.status.error)}`);
+                    return;
+                case "cancelled":
+                    exportFinished("Export cancelled");
+                    return;
+            }
+
+            if (data.progress.total > 0) {
+                exportStatus.innerText = `Exporting, ${data.progress.done} of ${data.progress.total} files`;
+            }
+            setTimeout(() => pollExport(id), 2000);
+        })
+        .catch(() => setTimeout(() => pollExport(id), 2000));
+}
+
+if (exportButton) {
+    exportButton.onclick = () => {
+        let map = exportButton.getAttribute("map");
+        let to = prompt("Save the exported zip to", `/blue/${map}.zip`);
+        if (!to) return;
+
+        exportButton.setAttribute("disabled", "disabled");
+        exportStatus.innerText = "Exporting";
+        fetch("/api/blue/v1/export", {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ token: getToken(), from: map, to: to, background: true }),
+        })
+            .then((response) => response.json())
+            .then((data) => {
+                if (data.type == "error") {
+                    exportFinished(`Export failed: ${JSON.stringify(data.kind)}`);
+                    return;
+                }
+                pollExport(data.id);
+            })
+            .catch((e) => exportFinished(`Export failed: ${e}`));
+    };
+}
+
+let versions = document.getElementById("versions");
+let deleteVersion = document.getElementById("delete-version");
+
+if (versions) {
+    let map = versions.getAttribute("map");
+    let viewer = document.getElementById("viewer");
+
+    versions.onchange = () => {
+        if (versions.value === "") {
+            viewer.src = `/fs/${map}/map`;
+            deleteVersion.classList.add("hide");
+        } else {
+            viewer.src = `/snapshot/${versions.value}/${map}/map`;
+            deleteVersion.classList.remove("hide");
+        }
+    };
+
+    deleteVersion.onclick = () => {
+        let id = versions.value;
+        if (id === "" || !confirm("Delete this snapshot? This cannot be undone.")) return;
+
+        fetch("/api/blue/v1/snapshot/delete", {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ token: getToken(), path: map, id: Number(id) }),
+        })
+            .then((response) => response.json())
+            .then((data) => {
+                if (data.type == "error") {
+                    alert(`Error deleting snapshot: ${JSON.stringify(data.kind)}`);
+                    return;
+                }
+                versions.querySelector(`option[value="${id}"]`).remove();
+                versions.value = "";
+                versions.onchange();
+            });
+    };
+}
+
+let watch = document.getElementById("watch");
+
+if (watch) {
+    let previous = watch.value;
+
+    watch.onchange = () => {
+        watch.setAttribute("disabled", "disabled");
+        fetch("/api/blue/v1/watch", {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ token: getToken(), path: watch.getAttribute("map"), state: watch.value }),
+        })
+            .then((response) => response.json())
+            .then((data) => {
+                if (data.type == "error") {
+                    alert(`Error changing auto update: ${JSON.stringify(data.kind)}`);
+                    watch.value = previous;
+                } else {
+                    previous = data.state;
+                    watch.value = data.state;
+                }
+            })
+            .catch(() => (watch.value = previous))
+            .finally(() => watch.removeAttribute("disabled"));
+    };
+}
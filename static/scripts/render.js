@@ -4,15 +4,24 @@ let failed = document.getElementById("failed");
 let timer = document.getElementById("timer");
 let viewerror = document.getElementById("viewerror");
 let reload = document.getElementById("reload");
+let progress = document.getElementById("progress");
+let eta = document.getElementById("eta");
+let queue = document.getElementById("queue");
+let log = document.getElementById("log");
+let cancel = document.getElementById("cancel");
 
 let params = new URL(window.location.toLocaleString()).searchParams;
 let source = params.get("source");
 let target = params.get("target");
+let update = params.get("update") == "true";
 
 let interval;
 let error;
+let jobId;
 
 let timerCounting = 0;
+// when the queue estimate was last fetched, it is only refreshed every few seconds
+let queueChecked = 0;
 let reloadCountdown = 6;
 
 function getCookie(name) {
@@ -25,22 +34,108 @@ function getToken() {
     return getCookie("token");
 }
 
+let presetSelect = document.getElementById("preset");
+let presetDescription = document.getElementById("preset-description");
+let presetDetails = document.getElementById("preset-details");
+let presetPreview = document.getElementById("preset-preview");
+
+function showPreset() {
+    let info = presetInfo[presetSelect.value];
+    if (!info) return;
+
+    presetDescription.innerText = info.description ?? info.name;
+
+    let details = [];
+    if (info.dimension) details.push(info.dimension);
+    if (info.ambient_light != null) details.push(`ambient light ${info.ambient_light}`);
+    if (info.hires != null) details.push(info.hires ? "hires" : "lowres only");
+    if (info.render_edges != null) details.push(info.render_edges ? "edges" : "no edges");
+    presetDetails.innerText = details.join(", ");
+
+    if (info.preview) {
+        presetPreview.src = info.preview;
+        presetPreview.classList.remove("hide");
+    } else {
+        presetPreview.classList.add("hide");
+    }
+}
+
+presetSelect.addEventListener("change", showPreset);
+showPreset();
+
 viewerror.onclick = () => alert(error);
 reload.onclick = window.reload;
 
-function errored(msg) {
+// errors the user can act on, shown in place of "Map render failed"
+function describeError(kind) {
+    switch (kind.type) {
+        case "not a world":
+            return "The source has no level.dat, pick a Minecraft world folder";
+        case "invalid level dat":
+            return `The world's level.dat could not be read: ${kind.reason}`;
+        case "unsupported world version":
+            return `Worlds from ${kind.version ?? `data version ${kind.data_version}`} are too old to render, open the world in Minecraft 1.13 or newer first`;
+        case "dimension not found":
+            return `The world has no ${kind.dimension} regions`;
+        case "invalid dimension":
+            return `${kind.dimension} is not a dimension id, use names such as minecraft:the_nether`;
+        case "invalid area":
+            return `Invalid render area: ${kind.reason}`;
+        case "no estimate":
+            return `No estimate: ${kind.reason}`;
+        case "invalid override":
+            return `Invalid ${kind.key}: ${kind.reason}`;
+        case "snapshots disabled":
+            return "Your account cannot keep previous versions of maps";
+        default:
+            return undefined;
+    }
+}
+
+function errored(msg, summary) {
     let mins = Math.floor(timerCounting / 60);
     let secs = timerCounting % 60;
     timer.innerText = `Rendering ended in ${mins.toString().padStart(2, "0")}:${secs.toString().padStart(2, "0")}`;
 
     clearInterval(interval);
+    cancel.classList.add("hide");
     error = msg;
-    failed.innerText = "Map render failed";
+    failed.innerText = summary ?? "Map render failed";
     failed.classList.remove("hide");
     viewerror.classList.remove("hide");
     reload.classList.remove("hide");
 }
 
+function cancelled() {
+    timer.innerText = `Rendering cancelled after ${formatDuration(timerCounting)}`;
+
+    clearInterval(interval);
+    cancel.classList.add("hide");
+    failed.innerText = "Map render cancelled";
+    failed.classList.remove("hide");
+    reload.classList.remove("hide");
+}
+
+cancel.onclick = () => {
+    if (jobId === undefined || !confirm("Cancel this render?")) return;
+    cancel.setAttribute("disabled", "disabled");
+
+    fetch("/api/blue/v1/cancel", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({ token: getToken(), id: jobId }),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.type == "error") {
+                cancel.removeAttribute("disabled");
+                alert(`Error cancelling render: ${JSON.stringify(data.kind)}`);
+            }
+        });
+};
+
 function completed() {
     let tickComplete = () => {
         reload.innerText = `Reloading in ${--reloadCountdown}`;
@@ -54,11 +149,112 @@ function completed() {
     timer.innerText = `Rendering ended in ${mins.toString().padStart(2, "0")}:${secs.toString().padStart(2, "0")}`;
 
     clearInterval(interval);
+    cancel.classList.add("hide");
     success.innerText = "Map render completed";
     success.classList.remove("hide");
     reload.classList.remove("hide");
 }
 
+let keepVersions = document.getElementById("keep-versions");
+
+function renderBody() {
+    let area = {};
+    for (let input of document.querySelectorAll(".area")) {
+        if (input.value !== "") area[input.name] = parseInt(input.value);
+    }
+
+    let overrides = {};
+    for (let input of document.querySelectorAll(".override")) {
+        if (input.value === "") continue;
+        switch (input.dataset.kind) {
+            case "bool":
+                overrides[input.name] = input.value === "true";
+                break;
+            case "number":
+                overrides[input.name] = Number(input.value);
+                break;
+            default:
+                overrides[input.name] = input.value;
+        }
+    }
+
+    return {
+        token: getToken(),
+        from: source,
+        to: target,
+        preset: document.getElementById("preset").value,
+        update: update,
+        dimensions: Array.from(document.querySelectorAll(".dimension:checked")).map(
+            (checkbox) => checkbox.value,
+        ),
+        area: area,
+        overrides: overrides,
+        keep_versions: keepVersions ? keepVersions.checked : undefined,
+    };
+}
+
+let estimate = document.getElementById("estimate");
+let estimateWarning = document.getElementById("estimate-warning");
+let estimateTimeout;
+
+function formatSize(bytes) {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let unit = 0;
+    while (bytes >= 1024 && unit < units.length - 1) {
+        bytes /= 1024;
+        unit++;
+    }
+    return `${bytes.toFixed(unit == 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+// dry run of the render with the current options, shown before it is started
+function showEstimate() {
+    let body = renderBody();
+    body.dry_run = true;
+
+    fetch("/api/blue/v1/render", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify(body),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            estimateWarning.classList.add("hide");
+            if (data.type == "error") {
+                estimate.innerText = describeError(data.kind) ?? "Could not estimate this render";
+                estimate.classList.remove("hide");
+                return;
+            }
+
+            let basis = data.rates.samples > 0 ? `based on ${data.rates.samples} past renders` : "rough guess";
+            estimate.innerText = `${data.regions} regions, ${data.chunks} chunks\n~${formatDuration(data.seconds)} and ${formatSize(data.size)} (${basis})`;
+            estimate.classList.remove("hide");
+
+            let warnings = [];
+            if (data.exceeds_timeout)
+                warnings.push(`Likely to run past the ${formatDuration(data.timeout)} time limit, try a smaller area`);
+            if (data.exceeds_quota)
+                warnings.push(`Likely to go over your storage quota (${formatSize(data.used)} of ${formatSize(data.quota)} used)`);
+            if (warnings.length > 0) {
+                estimateWarning.innerText = warnings.join("\n");
+                estimateWarning.classList.remove("hide");
+            }
+        })
+        .catch(() => estimate.classList.add("hide"));
+}
+
+function queueEstimate() {
+    clearTimeout(estimateTimeout);
+    estimateTimeout = setTimeout(showEstimate, 500);
+}
+
+for (let input of document.querySelectorAll("#preset, .dimension, .area")) {
+    input.addEventListener("change", queueEstimate);
+}
+showEstimate();
+
 startRender.onclick = () => {
     if (startRender.getAttribute("disabled") == "disabled") return;
     startRender.setAttribute("disabled", "disabled");
@@ -73,12 +269,8 @@ startRender.onclick = () => {
     tick();
     interval = setInterval(tick, 1000);
 
-    let body = {
-        token: getToken(),
-        from: source,
-        to: target,
-        preset: document.getElementById("preset").value,
-    };
+    let body = renderBody();
+    body.background = true;
 
     let url = "/api/blue/v1/render";
     fetch(url, {
@@ -91,11 +283,116 @@ startRender.onclick = () => {
         .then((response) => response.json())
         .then((data) => {
             if (data.type == "error") {
-                errored(JSON.stringify(data.kind));
+                errored(JSON.stringify(data.kind), describeError(data.kind));
                 return;
             }
 
-            completed();
+            jobId = data.id;
+            cancel.classList.remove("hide");
+            subscribe(data.id);
         })
         .catch((error) => errored(JSON.stringify(error)));
 };
+
+function formatDuration(seconds) {
+    let mins = Math.floor(seconds / 60);
+    let secs = Math.floor(seconds % 60);
+    return `${mins.toString().padStart(2, "0")}:${secs.toString().padStart(2, "0")}`;
+}
+
+// returns true once the render has finished
+function showStatus(data) {
+    let current = data.progress;
+
+    if (current.total > 0) {
+        progress.max = current.total;
+        progress.value = current.done;
+        progress.classList.remove("hide");
+    }
+
+    if (current.phase == "rendering" && current.done > 0 && current.started) {
+        let elapsed = Date.now() / 1000 - current.started;
+        let remaining = (elapsed / current.done) * (current.total - current.done);
+        eta.innerText = `${current.done}/${current.total} tiles, ${formatDuration(remaining)} remaining`;
+        eta.classList.remove("hide");
+    } else {
+        eta.innerText = current.phase;
+        eta.classList.remove("hide");
+    }
+
+    if (current.phase == "queued" && data.status.type == "queued") {
+        showQueue(data.id);
+    } else {
+        queue.classList.add("hide");
+    }
+
+    if (current.log.length > 0) {
+        log.innerText = current.log.join("\n");
+        log.classList.remove("hide");
+        log.scrollTop = log.scrollHeight;
+    }
+
+    switch (data.status.type) {
+        case "succeeded":
+            completed();
+            return true;
+        case "failed":
+            errored(JSON.stringify(data.status.error));
+            return true;
+        case "cancelled":
+            cancelled();
+            return true;
+        default:
+            return false;
+    }
+}
+
+function showQueue(id) {
+    if (Date.now() - queueChecked < 5000) return;
+    queueChecked = Date.now();
+
+    fetch(`/api/blue/v1/queue/${getToken()}/${id}`)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.type == "error" || data.position == null) {
+                queue.classList.add("hide");
+                return;
+            }
+
+            let ahead = data.ahead == 1 ? "1 job" : `${data.ahead} jobs`;
+            queue.innerText = `#${data.position} in the queue, ${ahead} ahead, ${data.running}/${data.slots} running
+starts in ~${formatDuration(data.starts_in)}, done in ~${formatDuration(data.finishes_in)}`;
+            queue.classList.remove("hide");
+        })
+        .catch(() => queue.classList.add("hide"));
+}
+
+function subscribe(id) {
+    let events = new EventSource(`/api/blue/v1/progress/${getToken()}/${id}`);
+    let finished = false;
+
+    events.onmessage = (event) => {
+        finished = showStatus(JSON.parse(event.data));
+        if (finished) events.close();
+    };
+
+    // fall back to polling if the stream gets cut, e.g. by a proxy
+    events.onerror = () => {
+        events.close();
+        if (!finished) poll(id);
+    };
+}
+
+function poll(id) {
+    fetch(`/api/blue/v1/status/${getToken()}/${id}`)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.type == "error") {
+                errored(JSON.stringify(data.kind));
+                return;
+            }
+
+            if (!showStatus(data)) setTimeout(() => poll(id), 2000);
+        })
+        .catch(() => setTimeout(() => poll(id), 2000));
+}